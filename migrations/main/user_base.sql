@@ -11,3 +11,7 @@ CREATE TABLE IF NOT EXISTS user_base
     UNIQUE (phone),
     UNIQUE (id)
 );
+
+CREATE INDEX IF NOT EXISTS user_base_search_idx ON user_base USING GIN (
+    to_tsvector('simple', coalesce(first_name, '') || ' ' || coalesce(last_name, '') || ' ' || coalesce(email, ''))
+);
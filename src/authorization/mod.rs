@@ -0,0 +1,4 @@
+pub mod permission;
+pub mod role;
+pub mod role_internal_user;
+pub mod role_permission;
@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "authorization_role")]
+pub struct Model {
+    #[sea_orm(unique)]
+    pub id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub name: String,
+    pub description: Option<String>,
+    pub created_on: DateTimeWithTimeZone,
+    pub updated_on: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "crate::authorization::role_internal_user::Entity")]
+    RoleInternalUser,
+    #[sea_orm(has_many = "crate::authorization::role_permission::Entity")]
+    RolePermission,
+}
+
+impl Related<crate::authorization::role_internal_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoleInternalUser.def()
+    }
+}
+
+impl Related<crate::authorization::role_permission::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RolePermission.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,46 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "authorization_role_internal_user")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub role_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub internal_user_id: i32,
+    pub description: Option<String>,
+    pub created_on: DateTimeWithTimeZone,
+    pub updated_on: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::authorization::role::Entity",
+        from = "Column::RoleId",
+        to = "crate::authorization::role::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Role,
+    #[sea_orm(
+        belongs_to = "crate::users::internal_user::Entity",
+        from = "Column::InternalUserId",
+        to = "crate::users::internal_user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    InternalUser,
+}
+
+impl Related<crate::authorization::role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
+}
+
+impl Related<crate::users::internal_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::InternalUser.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
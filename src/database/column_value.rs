@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
+use sea_orm::prelude::{Decimal, Uuid};
+use sea_orm::{ColumnType, Value};
+
+use crate::database::relative_date::resolve_relative_date;
+
+/// Parses a filter value into the type of the column it's compared against, the error
+/// describes the value that was expected
+pub fn column_value(column_type: &ColumnType, input: &str) -> Result<Value, &'static str> {
+    let (value, expected) = match column_type {
+        ColumnType::TinyInteger | ColumnType::SmallInteger | ColumnType::Integer => {
+            (input.parse::<i32>().ok().map(Value::from), "an integer")
+        }
+        ColumnType::BigInteger => {
+            (input.parse::<i64>().ok().map(Value::from), "an integer")
+        }
+        ColumnType::TinyUnsigned | ColumnType::SmallUnsigned | ColumnType::Unsigned => {
+            (input.parse::<u32>().ok().map(Value::from), "a positive integer")
+        }
+        ColumnType::BigUnsigned => {
+            (input.parse::<u64>().ok().map(Value::from), "a positive integer")
+        }
+        ColumnType::Float => {
+            (input.parse::<f32>().ok().map(Value::from), "a number")
+        }
+        ColumnType::Double => {
+            (input.parse::<f64>().ok().map(Value::from), "a number")
+        }
+        ColumnType::Decimal(_) | ColumnType::Money(_) => {
+            (Decimal::from_str(input).ok().map(Value::from), "a decimal number")
+        }
+        ColumnType::Boolean => {
+            (input.parse::<bool>().ok().map(Value::from), "true or false")
+        }
+        ColumnType::Uuid => {
+            (Uuid::parse_str(input).ok().map(Value::from), "a UUID")
+        }
+        ColumnType::Date => {
+            (NaiveDate::parse_from_str(input, "%Y-%m-%d").ok().map(Value::from), "a date (YYYY-MM-DD)")
+        }
+        ColumnType::Time => {
+            (NaiveTime::from_str(input).ok().map(Value::from), "a time (HH:MM:SS)")
+        }
+        ColumnType::DateTime | ColumnType::Timestamp => {
+            (parse_date_time(input).map(|date_time| Value::from(date_time.naive_utc())), "a date, an RFC 3339 timestamp or a relative date")
+        }
+        ColumnType::TimestampWithTimeZone => {
+            (parse_date_time(input).map(Value::from), "a date, an RFC 3339 timestamp or a relative date")
+        }
+        _ => {
+            (Some(Value::from(input)), "")
+        }
+    };
+
+    value.ok_or(expected)
+}
+
+/// Parses a comma separated list of filter values, as used by `in`, `nin` and `between`
+pub fn column_values(column_type: &ColumnType, input: &str) -> Result<Vec<Value>, &'static str> {
+    input
+        .split(',')
+        .map(|item| column_value(column_type, item))
+        .collect()
+}
+
+/// Rewrites a relative date value such as `now-7d` into an absolute one for date and timestamp
+/// columns, any other value is returned unchanged
+pub fn resolve_relative_value(column_type: &ColumnType, input: &str, now: DateTime<Utc>) -> String {
+    let resolved = match column_type {
+        ColumnType::Date => {
+            resolve_relative_date(input, now).map(|date_time| date_time.format("%Y-%m-%d").to_string())
+        }
+        ColumnType::DateTime | ColumnType::Timestamp | ColumnType::TimestampWithTimeZone => {
+            resolve_relative_date(input, now).map(|date_time| date_time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+        _ => None,
+    };
+
+    resolved.unwrap_or_else(|| input.to_string())
+}
+
+/// Accepts an RFC 3339 timestamp, a timestamp without offset taken as UTC, or a date at midnight UTC
+fn parse_date_time(input: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(input) {
+        return Some(date_time);
+    }
+
+    // an unencoded `+` of the offset arrives as a space after form decoding
+    if let Some(space) = input.rfind(' ') {
+        let restored = format!("{}+{}", &input[..space], &input[space + 1..]);
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(&restored) {
+            return Some(date_time);
+        }
+    }
+
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(date_time.and_utc().fixed_offset());
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Some(date.and_hms_opt(0, 0, 0)?.and_utc().fixed_offset());
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::Value;
+
+    use chrono::{TimeZone, Utc};
+    use sea_orm::ColumnTrait;
+
+    use crate::database::column_value::{column_value, column_values, resolve_relative_value};
+    use crate::users::user;
+
+    #[test]
+    fn given_integer_column_when_value_is_number_should_bind_integer() {
+        let value = column_value(user::Column::Id.def().get_column_type(), "42").unwrap();
+
+        assert_eq!(value, Value::from(42));
+    }
+
+    #[test]
+    fn given_integer_column_when_value_overflows_should_fail() {
+        let error = column_value(user::Column::Id.def().get_column_type(), "3000000000").unwrap_err();
+
+        assert_eq!(error, "an integer");
+    }
+
+    #[test]
+    fn given_integer_column_when_value_is_text_should_fail() {
+        assert!(column_value(user::Column::Id.def().get_column_type(), "abc").is_err());
+        assert!(column_value(user::Column::Id.def().get_column_type(), "1.5").is_err());
+    }
+
+    #[test]
+    fn given_text_column_when_value_is_number_should_bind_string() {
+        let value = column_value(user::Column::Phone.def().get_column_type(), "5555").unwrap();
+
+        assert_eq!(value, Value::from("5555"));
+    }
+
+    #[test]
+    fn given_timestamp_column_when_value_is_rfc3339_should_bind_timestamp() {
+        let value = column_value(user::Column::CreatedOn.def().get_column_type(), "2024-01-02T03:04:05+02:00").unwrap();
+
+        assert!(matches!(value, Value::ChronoDateTimeWithTimeZone(Some(_))));
+    }
+
+    #[test]
+    fn given_timestamp_column_when_offset_plus_was_form_decoded_should_restore_it() {
+        let column = user::Column::CreatedOn.def();
+        let value = column_value(column.get_column_type(), "2024-01-01T00:00:00 02:00").unwrap();
+
+        assert_eq!(value, column_value(column.get_column_type(), "2024-01-01T00:00:00+02:00").unwrap());
+        assert_eq!(
+            column_value(column.get_column_type(), "2024-01-01 00:00:00 02:00").unwrap(),
+            column_value(column.get_column_type(), "2023-12-31T22:00:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn given_timestamp_column_when_value_is_date_should_bind_midnight_utc() {
+        let value = column_value(user::Column::CreatedOn.def().get_column_type(), "2024-01-02").unwrap();
+        let expected = column_value(user::Column::CreatedOn.def().get_column_type(), "2024-01-02T00:00:00Z").unwrap();
+
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn given_timestamp_column_when_value_is_invalid_should_fail() {
+        assert!(column_value(user::Column::CreatedOn.def().get_column_type(), "yesterday-ish").is_err());
+    }
+
+    #[test]
+    fn given_value_list_when_one_item_is_invalid_should_fail() {
+        assert!(column_values(user::Column::Id.def().get_column_type(), "1,2,3").is_ok());
+        assert!(column_values(user::Column::Id.def().get_column_type(), "1,x,3").is_err());
+    }
+
+    #[test]
+    fn given_timestamp_column_when_value_is_relative_should_resolve_it() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 14, 15, 9, 26).unwrap();
+
+        assert_eq!(resolve_relative_value(user::Column::CreatedOn.def().get_column_type(), "now-7d", now), "2024-03-07T15:09:26Z");
+        assert_eq!(resolve_relative_value(user::Column::CreatedOn.def().get_column_type(), "2024-01-01", now), "2024-01-01");
+        assert_eq!(resolve_relative_value(user::Column::FirstName.def().get_column_type(), "today", now), "today");
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shortest `CURSOR_SECRET` accepted, as many bytes as the HMAC-SHA256 output
+pub const MIN_SECRET_LENGTH: usize = 32;
+
+static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Position of a row in a sorted list, handed out as an opaque token in `next` and `previous`
+/// and read back from `cursor=`. The values are the row's sort key in the order of `sort`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Cursor {
+    /// Sort the values belong to, a token is only valid for the same sort
+    #[serde(rename = "s")]
+    pub sort: String,
+    #[serde(rename = "v")]
+    pub values: Vec<JsonValue>,
+    /// Index of the row in the list when the cursor was issued, gives the page number without
+    /// counting the rows before it
+    #[serde(rename = "p", default)]
+    pub position: u64,
+    /// Whether the page ends just before the row instead of starting just after it
+    #[serde(rename = "b", default)]
+    pub backward: bool,
+}
+
+impl Cursor {
+    /// Encodes the cursor as `<payload>.<signature>`, both base64url without padding
+    pub fn encode(&self, secret: &[u8]) -> String {
+        let payload = serde_json::to_vec(self).unwrap_or_default();
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(&payload);
+
+        format!("{}.{}", URL_SAFE_NO_PAD.encode(&payload), URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    /// Reads a token made by [`Cursor::encode`], `None` when it is malformed or was altered
+    pub fn decode(token: &str, secret: &[u8]) -> Option<Cursor> {
+        let (payload, signature) = token.split_once('.')?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        let mut mac = HmacSha256::new_from_slice(secret).ok()?;
+        mac.update(&payload);
+        mac.verify_slice(&signature).ok()?;
+
+        serde_json::from_slice(&payload).ok()
+    }
+}
+
+/// Sets the key cursor tokens are signed with, read from `CURSOR_SECRET` at startup. The key
+/// has to be the same on every instance for tokens to work across them and restarts.
+pub fn init_cursor_secret(secret: String) -> Result<(), String> {
+    if secret.len() < MIN_SECRET_LENGTH {
+        return Err(format!("CURSOR_SECRET must be at least {} bytes long", MIN_SECRET_LENGTH));
+    }
+
+    SECRET.set(secret.into_bytes()).map_err(|_| "CURSOR_SECRET is already set".to_string())
+}
+
+/// Key set by [`init_cursor_secret`], tests sign with a fixed key instead
+pub fn cursor_secret() -> &'static [u8] {
+    if cfg!(test) {
+        return SECRET.get_or_init(|| b"cursor secret used by the tests only".to_vec());
+    }
+
+    SECRET.get().expect("CURSOR_SECRET is set at startup")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::database::cursor::{init_cursor_secret, Cursor};
+
+    fn cursor() -> Cursor {
+        Cursor {
+            sort: "created_on:desc:nulls_first,id:asc:nulls_last".to_string(),
+            values: vec![json!("2024-01-02T03:04:05+00:00"), json!(42)],
+            position: 199,
+            backward: false,
+        }
+    }
+
+    #[test]
+    fn given_encoded_cursor_should_decode_with_same_secret() {
+        let token = cursor().encode(b"secret");
+
+        assert_eq!(Cursor::decode(&token, b"secret"), Some(cursor()));
+        assert_eq!(Cursor::decode(&token, b"other secret"), None);
+    }
+
+    #[test]
+    fn given_altered_token_should_not_decode() {
+        let token = cursor().encode(b"secret");
+        let (_, signature) = token.split_once('.').unwrap();
+        let mut forged = cursor();
+        forged.values[1] = json!(1);
+        let forged = forged.encode(b"secret");
+        let (forged_payload, _) = forged.split_once('.').unwrap();
+
+        assert_eq!(Cursor::decode(&format!("{}.{}", forged_payload, signature), b"secret"), None);
+        assert_eq!(Cursor::decode("not-a-cursor", b"secret"), None);
+        assert_eq!(Cursor::decode(&token[1..], b"secret"), None);
+    }
+
+    #[test]
+    fn given_short_secret_should_be_rejected() {
+        assert_eq!(init_cursor_secret("secret".to_string()), Err("CURSOR_SECRET must be at least 32 bytes long".to_string()));
+    }
+}
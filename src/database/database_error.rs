@@ -0,0 +1,150 @@
+use http::StatusCode;
+use sea_orm::{ConnAcquireErr, DbErr, RuntimeErr};
+use sqlx::Error as SqlxError;
+use tracing::{error, warn};
+
+use crate::global::error_handling::ErrorDetails;
+
+/// Seconds a client is asked to wait before retrying while the database is unreachable
+pub const RETRY_AFTER_SECONDS: u64 = 5;
+
+/// Client facing error of a failed database call. Only the status and a safe message are
+/// returned, the error itself can hold SQL, values and constraint names so it is only logged.
+pub fn database_error(error: DbErr) -> Vec<ErrorDetails> {
+    let details = classify(&error);
+    if details.status_code.is_server_error() {
+        error!("Database error, responding {}: {}", details.status_code, error);
+    } else {
+        warn!("Database error, responding {}: {}", details.status_code, error);
+    }
+
+    vec![details]
+}
+
+fn classify(error: &DbErr) -> ErrorDetails {
+    match error {
+        DbErr::ConnectionAcquire(ConnAcquireErr::Timeout) => unavailable("No database connection is available, try again later"),
+        DbErr::ConnectionAcquire(ConnAcquireErr::ConnectionClosed) | DbErr::Conn(_) => connection_lost(),
+        DbErr::Exec(RuntimeErr::SqlxError(error)) | DbErr::Query(RuntimeErr::SqlxError(error)) => match error {
+            SqlxError::Database(error) => sql_state_error(error.code().as_deref().unwrap_or_default()),
+            SqlxError::PoolTimedOut => unavailable("No database connection is available, try again later"),
+            SqlxError::Io(_) | SqlxError::Tls(_) | SqlxError::PoolClosed | SqlxError::WorkerCrashed => connection_lost(),
+            _ => internal(),
+        },
+        _ => internal(),
+    }
+}
+
+/// Classifies a Postgres error by its SQLSTATE code
+fn sql_state_error(code: &str) -> ErrorDetails {
+    match code {
+        "23505" => ErrorDetails {
+            status_code: StatusCode::CONFLICT,
+            message: "A record with the same unique value already exists".to_string(),
+            retry_after: None,
+        },
+        "23503" => ErrorDetails {
+            status_code: StatusCode::UNPROCESSABLE_ENTITY,
+            message: "A referenced record doesn't exist or is still referenced".to_string(),
+            retry_after: None,
+        },
+        // serialization_failure and deadlock_detected, the transaction can succeed when retried
+        "40001" | "40P01" => ErrorDetails {
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+            message: "The request conflicted with a concurrent one, try again".to_string(),
+            retry_after: None,
+        },
+        // query_canceled by statement_timeout and lock_not_available by lock_timeout
+        "57014" | "55P03" => ErrorDetails {
+            status_code: StatusCode::SERVICE_UNAVAILABLE,
+            message: "The database took too long to respond".to_string(),
+            retry_after: None,
+        },
+        // too_many_connections, admin_shutdown, crash_shutdown and cannot_connect_now
+        "53300" | "57P01" | "57P02" | "57P03" => connection_lost(),
+        // connection_exception class
+        code if code.starts_with("08") => connection_lost(),
+        _ => internal(),
+    }
+}
+
+fn connection_lost() -> ErrorDetails {
+    unavailable("The database is unavailable, try again later")
+}
+
+fn unavailable(message: &str) -> ErrorDetails {
+    ErrorDetails {
+        status_code: StatusCode::SERVICE_UNAVAILABLE,
+        message: message.to_string(),
+        retry_after: Some(RETRY_AFTER_SECONDS),
+    }
+}
+
+fn internal() -> ErrorDetails {
+    ErrorDetails {
+        status_code: StatusCode::INTERNAL_SERVER_ERROR,
+        message: "The database request failed".to_string(),
+        retry_after: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use http::StatusCode;
+    use sea_orm::{ConnAcquireErr, DbErr, RuntimeErr};
+    use sqlx::Error as SqlxError;
+
+    use crate::database::database_error::{classify, sql_state_error, RETRY_AFTER_SECONDS};
+
+    #[test]
+    fn given_constraint_violations_should_return_client_errors() {
+        let unique = sql_state_error("23505");
+        let foreign_key = sql_state_error("23503");
+
+        assert_eq!(unique.status_code, StatusCode::CONFLICT);
+        assert_eq!(unique.message, "A record with the same unique value already exists");
+        assert_eq!(foreign_key.status_code, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!((unique.retry_after, foreign_key.retry_after), (None, None));
+    }
+
+    #[test]
+    fn given_transient_sql_states_should_return_503() {
+        for code in ["40001", "40P01", "57014", "55P03"] {
+            let error = sql_state_error(code);
+
+            assert_eq!(error.status_code, StatusCode::SERVICE_UNAVAILABLE, "{}", code);
+            assert_eq!(error.retry_after, None, "{}", code);
+        }
+        for code in ["08006", "57P01", "53300"] {
+            assert_eq!(sql_state_error(code).retry_after, Some(RETRY_AFTER_SECONDS), "{}", code);
+        }
+    }
+
+    #[test]
+    fn given_lost_connection_should_return_503_with_retry_after() {
+        let errors = [
+            DbErr::Conn(RuntimeErr::Internal("refused".to_string())),
+            DbErr::ConnectionAcquire(ConnAcquireErr::Timeout),
+            DbErr::Query(RuntimeErr::SqlxError(SqlxError::Io(io::Error::from(io::ErrorKind::ConnectionReset)))),
+            DbErr::Query(RuntimeErr::SqlxError(SqlxError::PoolClosed)),
+        ];
+
+        for error in errors {
+            let details = classify(&error);
+
+            assert_eq!(details.status_code, StatusCode::SERVICE_UNAVAILABLE, "{}", error);
+            assert_eq!(details.retry_after, Some(RETRY_AFTER_SECONDS), "{}", error);
+        }
+    }
+
+    #[test]
+    fn given_other_errors_should_not_expose_details() {
+        let error = classify(&DbErr::Query(RuntimeErr::Internal("column \"secret\" does not exist".to_string())));
+
+        assert_eq!(error.status_code, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.message, "The database request failed");
+        assert_eq!(sql_state_error("42703").status_code, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -1 +1,7 @@
-pub mod query_builder;
\ No newline at end of file
+pub mod column_value;
+pub mod cursor;
+pub mod database_error;
+pub mod query_builder;
+pub mod query_guard;
+pub mod query_resource;
+pub mod relative_date;
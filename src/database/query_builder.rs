@@ -1,192 +1,539 @@
-use http::StatusCode;
-use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, IdenStatic, Iterable, QueryFilter as QF, QueryOrder, QuerySelect, Select};
+use std::collections::HashMap;
 
+use change_case::camel_case;
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::{Alias, Asterisk, ConditionExpression, DynIden, Expr, Func, NullOrdering, Order, Query, SelectStatement, SimpleExpr, TableRef};
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::{ColumnTrait, ColumnType, Condition, ConnectionTrait, DatabaseConnection, EntityTrait, FromQueryResult, IdenStatic, Iterable, JoinType, QueryFilter as QF, JsonValue, PaginatorTrait, QueryOrder, QuerySelect, QueryTrait, RelationDef, Select, Value};
+
+use crate::database::cursor::{cursor_secret, Cursor};
+use crate::database::database_error::database_error;
+use crate::database::query_guard::{begin_guarded, check_cost, query_plan};
+use crate::database::column_value::{column_value, column_values, resolve_relative_value};
+use crate::database::query_resource::{QueryField, QueryRelation, QueryResource, DEFAULT_LIMIT};
 use crate::global::error_handling::ErrorDetails;
-use crate::global::parameter_query_builder::{ParameterQueryResult, QueryFilter, QuerySort};
-use crate::global::response_builder::MetaListData;
+use crate::global::filter_expression::FilterExpression;
+use crate::global::parameter_query_builder::{ColumnAggregate, ColumnFilter, ColumnSort, ParameterQueryResult, QueryAggregate, QueryCount, QueryFilter, QueryNulls, QueryOperator, QuerySort, TextSearch, RELEVANCE};
+use crate::global::response_builder::{MetaAggregateData, MetaListData};
+
+/// Column alias of the parent key on rows loaded for an include
+const INCLUDE_KEY: &str = "__include_key";
+
+/// Column alias prefix of the sort key values selected to build cursors from
+const CURSOR_KEY: &str = "__cursor_";
+
+/// Text search configuration of `q=`, `simple` doesn't stem so names and emails match as written
+const SEARCH_CONFIGURATION: &str = "simple";
 
 pub struct QueryResult<T> {
     pub data: Vec<T>,
     pub meta: MetaListData,
 }
 
+pub struct AggregateResult<T> {
+    pub data: Vec<T>,
+    pub meta: MetaAggregateData,
+}
+
 pub struct QueryBuilder;
 
 impl QueryBuilder {
-    pub async fn get_list<E: EntityTrait>(
+    /// Runs an already validated query, see [`QueryBuilder::validate`], for a single page and
+    /// embeds the requested includes with one query per include.
+    ///
+    /// One row past the limit is read to tell whether another page follows, the cursors of the
+    /// pages around it come from the first and last row. Cursors carry the position of their
+    /// row, so the page number doesn't need the rows before it counted. The total is counted
+    /// only when the page doesn't show where the list ends.
+    ///
+    /// The queries run in one transaction under the request limits of
+    /// [`query_limits`](crate::database::query_guard::query_limits).
+    pub async fn paginate<E: QueryResource>(
         db: &DatabaseConnection,
         query_result: ParameterQueryResult,
-    ) -> Result<Vec<<E as EntityTrait>::Model>, Vec<ErrorDetails>>
+    ) -> Result<QueryResult<JsonValue>, Vec<ErrorDetails>>
+        where
+            E::Model: Sync,
     {
-        let base_query = QueryBuilder::generate(E::find(), query_result);
+        let limit = query_result.limit.unwrap_or(E::default_limit());
+        let skip = query_result.skip(limit);
+        let keyset = query_result.keyset.clone();
+        let backward = keyset.as_ref().is_some_and(|keyset| keyset.backward);
+        let sort = sort_signature(&query_result.sort_list);
+        let key_count = query_result.sort_list.len();
+
+        let mut count_query = query_result.clone();
+        count_query.keyset = None;
+
+        let mut page_query = query_result;
+        page_query.limit = Some(limit + 1);
+        page_query.page = None;
+        page_query.offset = skip;
+
+        let transaction = begin_guarded(db).await?;
+        let mut rows = find_rows::<E, _>(&transaction, page_query).await?;
+        let has_more = rows.len() as u64 > limit;
+        rows.truncate(limit as usize);
+        if backward {
+            rows.reverse();
+        }
+
+        let positions: Vec<Vec<JsonValue>> = rows
+            .iter_mut()
+            .map(|row| {
+                (0..key_count)
+                    .map(|index| {
+                        row.as_object_mut()
+                            .and_then(|object| object.remove(&cursor_alias(index)))
+                            .unwrap_or(JsonValue::Null)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // going backward the rows were read in reverse, so the extra row lies before the page
+        let has_next = if backward { keyset.is_some() } else { has_more };
+        let has_previous = if backward { has_more } else { keyset.is_some() || skip.is_some_and(|skip| skip > 0) };
+
+        let page_size = rows.len() as u64;
+        let preceding_count = preceding_count(keyset.as_ref(), skip, page_size, has_more);
+
+        let next = match positions.last() {
+            Some(values) if has_next => Some(Cursor {
+                sort: sort.clone(),
+                values: values.clone(),
+                position: preceding_count + page_size - 1,
+                backward: false,
+            }),
+            _ => None,
+        };
+        let previous = match positions.first() {
+            Some(values) if has_previous => Some(Cursor {
+                sort,
+                values: values.clone(),
+                position: preceding_count,
+                backward: true,
+            }),
+            _ => None,
+        };
+
+        let known_count = preceding_count + page_size + has_more as u64;
+        let total_count = match count_query.count {
+            QueryCount::NONE => None,
+            _ if ends_list(keyset.as_ref(), skip, page_size, has_more) => Some(known_count),
+            QueryCount::EXACT => {
+                let mut count_select = QueryBuilder::generate(E::find(), count_query);
+                QueryTrait::query(&mut count_select).reset_limit().reset_offset();
+                check_cost(&transaction, &count_select).await?;
 
-        match base_query
-            .all(db)
-            .await {
-            Ok(result) => {
-                Ok(result)
+                let count = count_select
+                    .count(&transaction)
+                    .await
+                    .map_err(database_error)?;
+                Some(count)
             }
-            Err(_error) => {
-                Err(vec![ErrorDetails {
-                    status_code: StatusCode::INTERNAL_SERVER_ERROR,
-                    message: "".to_string(),
-                }])
+            QueryCount::ESTIMATE => {
+                let estimate = estimate_count(&transaction, QueryBuilder::generate(E::find(), count_query)).await?;
+                Some(estimate.max(known_count))
             }
-        }
+        };
+        transaction.commit().await.map_err(database_error)?;
+
+        Ok(QueryResult {
+            meta: MetaListData {
+                timestamp: Utc::now(),
+                count: total_count,
+                page: QueryBuilder::current_page(preceding_count, limit),
+                page_count: total_count.map(|total_count| QueryBuilder::page_count(total_count, limit)),
+                limit,
+                next: next.map(|cursor| cursor.encode(cursor_secret())),
+                previous: previous.map(|cursor| cursor.encode(cursor_secret())),
+            },
+            data: rows,
+        })
     }
 
-    pub fn generate<E: EntityTrait>(select: Select<E>, query_result: ParameterQueryResult) -> Select<E> {
-        let mut base_query = select.limit(u64::from(query_result.limit));
+    /// Checks the query against the resource settings, fills in its defaults and maps
+    /// public field names to their columns
+    pub fn validate<E: QueryResource>(mut query_result: ParameterQueryResult) -> Result<ParameterQueryResult, Vec<ErrorDetails>> {
+        let mut errors: Vec<ErrorDetails> = vec![];
+
+        let limit = query_result.limit.unwrap_or(E::default_limit());
+        if limit > E::max_limit() {
+            errors.push(ErrorDetails::bad_request(format!(
+                "Limit {} exceeds the maximum of {}",
+                limit,
+                E::max_limit()
+            )));
+        }
 
-        // TODO: Move sort logic to function
-        for sort in query_result.sort_list {
-            match sort.0 {
-                QuerySort::ASC => {
-                    for column in E::Column::iter() {
-                        let column_name: &str = &column.as_str();
+        query_result.limit = Some(limit);
 
-                        for item in &sort.1 {
-                            let deref_item: String = item.into();
+        if let Some(page) = query_result.page.filter(|_| query_result.skip(limit).is_none()) {
+            errors.push(ErrorDetails::bad_request(format!(
+                "Page {} is out of range for a limit of {}",
+                page,
+                limit
+            )));
+        }
 
-                            if deref_item == column_name {
-                                base_query = base_query.clone().order_by_asc(column);
+        if let Err(field_errors) = QueryBuilder::select_columns::<E>(&query_result.fields) {
+            errors.extend(field_errors);
+        }
 
-                                break;
-                            }
-                        }
+        let query_fields = E::query_fields();
+        let relations = E::relations();
+        let now = query_result.requested_at;
+        for filter_list in query_result.filter_list.iter_mut() {
+            for filter in filter_list.filter_list.iter_mut() {
+                if let Err(error) = resolve_filter::<E>(&query_fields, &relations, filter, now) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        if let Some(filter_expression) = query_result.filter_expression.as_mut() {
+            resolve_expression::<E>(&query_fields, &relations, filter_expression, now, &mut errors);
+        }
+
+        for path in &query_result.include {
+            match relations.iter().find(|relation| &relation.path == path && relation.includable) {
+                Some(relation) => {
+                    let Some(key) = parent_key(relation) else {
+                        continue;
+                    };
+
+                    let key = response_key(&query_fields, &key);
+                    if !query_result.fields.is_empty() && !query_result.fields.contains(&key) {
+                        errors.push(ErrorDetails::bad_request(format!("Including '{}' requires the '{}' field", path, key)));
                     }
                 }
-                QuerySort::DESC => {
-                    for column in E::Column::iter() {
-                        let column_name: &str = &column.as_str();
+                None => {
+                    errors.push(ErrorDetails::bad_request(format!("Unknown include '{}'", path)));
+                }
+            }
+        }
+
+        for group in query_result.group_list.iter_mut() {
+            let Some(resolved) = resolve_property::<E>(&query_fields, &relations, &group.property) else {
+                errors.push(ErrorDetails::bad_request(format!("Unknown group property '{}'", group.property)));
+                continue;
+            };
+
+            if let Some(truncate) = &group.truncate {
+                if !resolved.column_type.as_ref().is_some_and(is_date_type) {
+                    errors.push(ErrorDetails::bad_request(format!(
+                        "Grouping by {} requires a date field, '{}' is not one",
+                        truncate.as_str(),
+                        group.property
+                    )));
+                }
+            }
 
-                        for item in &sort.1 {
-                            let deref_item: String = item.into();
+            group.property = resolved.property;
+        }
 
-                            if deref_item == column_name {
-                                base_query = base_query.clone().order_by_desc(column);
+        for aggregate in query_result.aggregate_list.iter_mut() {
+            let Some(property) = aggregate.property.as_mut() else {
+                continue;
+            };
 
-                                break;
-                            }
-                        }
+            match resolve_property::<E>(&query_fields, &relations, property) {
+                Some(resolved) if resolved.to_many => {
+                    errors.push(ErrorDetails::bad_request(format!("Aggregating '{}' is not allowed", property)));
+                }
+                Some(resolved) => {
+                    let numeric = resolved.column_type.as_ref().is_some_and(is_numeric_type);
+                    if matches!(aggregate.function, QueryAggregate::SUM | QueryAggregate::AVG) && !numeric {
+                        errors.push(ErrorDetails::bad_request(format!(
+                            "Aggregate '{}' requires a numeric field, '{}' is not one",
+                            aggregate.function.as_str(),
+                            property
+                        )));
                     }
+
+                    *property = resolved.property;
+                }
+                None => {
+                    errors.push(ErrorDetails::bad_request(format!("Unknown aggregate property '{}'", property)));
+                }
+            }
+        }
+
+        if let Some(search) = query_result.search.as_mut() {
+            search.columns = E::search_columns().into_iter().map(String::from).collect();
+            if search.columns.is_empty() {
+                errors.push(ErrorDetails::bad_request("Search with 'q' is not supported on this resource".to_string()));
+            }
+        }
+
+        for column_sort in query_result.sort_list.iter_mut() {
+            if column_sort.property == RELEVANCE {
+                if query_result.search.is_none() {
+                    errors.push(ErrorDetails::bad_request(format!("Sorting on '{}' requires a 'q' search", RELEVANCE)));
+                }
+
+                continue;
+            }
+
+            match resolve_property::<E>(&query_fields, &relations, &column_sort.property) {
+                Some(resolved) if resolved.field.sortable && !resolved.to_many => {
+                    column_sort.property = resolved.property;
+                }
+                Some(_) => {
+                    errors.push(ErrorDetails::bad_request(format!("Sorting on '{}' is not allowed", column_sort.property)));
+                }
+                None => {
+                    errors.push(ErrorDetails::bad_request(format!("Unknown sort property '{}'", column_sort.property)));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if !query_result.sort_list.iter().any(|column_sort| column_sort.property == E::cursor_column()) {
+            query_result.sort_list.push(ColumnSort {
+                property: E::cursor_column().to_string(),
+                sort: QuerySort::ASC,
+                nulls: None,
+            });
+        }
+
+        if let Some(token) = &query_result.cursor {
+            let search = query_result.search.as_ref().and_then(search_document::<E>);
+            let keys = sort_keys::<E>(&relations, search.as_ref(), &query_result.sort_list);
+
+            match Cursor::decode(token, cursor_secret()) {
+                Some(cursor) if cursor.sort != sort_signature(&query_result.sort_list) => {
+                    return Err(vec![ErrorDetails::bad_request("Cursor was issued for a different sort_by".to_string())]);
+                }
+                Some(cursor) if keyset_values(&keys, &cursor.values).is_some() => {
+                    query_result.keyset = Some(cursor);
+                }
+                _ => {
+                    return Err(vec![ErrorDetails::bad_request("Invalid cursor".to_string())]);
                 }
             }
         }
 
-        // TODO: Build filter
+        Ok(query_result)
+    }
+
+    /// Runs an already validated aggregate query, see [`QueryBuilder::generate_aggregate`],
+    /// under the same request limits as [`QueryBuilder::paginate`]. Returns the groups of the
+    /// page and whether more groups follow it, read from one group past the limit.
+    pub async fn get_aggregate<E: QueryResource>(
+        db: &DatabaseConnection,
+        mut query_result: ParameterQueryResult,
+    ) -> Result<(Vec<JsonValue>, bool), Vec<ErrorDetails>>
+    {
+        let limit = query_result.limit.unwrap_or(E::default_limit());
+        query_result.offset = query_result.skip(limit);
+        query_result.page = None;
+        query_result.limit = Some(limit + 1);
+
+        let select = QueryBuilder::generate_aggregate(E::find(), query_result);
+        let transaction = begin_guarded(db).await?;
+        check_cost(&transaction, &select).await?;
+
+        let mut groups = select
+            .into_json()
+            .all(&transaction)
+            .await
+            .map_err(database_error)?;
+        transaction.commit().await.map_err(database_error)?;
+
+        let has_more = groups.len() as u64 > limit;
+        groups.truncate(limit as usize);
+
+        Ok((groups, has_more))
+    }
+
+    /// Builds one row per group of a validated query with the filters applied, holding the group
+    /// values and the aggregates under the names they were requested with. Groups are ordered
+    /// by their values, `count(*)` is used when no aggregate was requested.
+    pub fn generate_aggregate<E: QueryResource>(select: Select<E>, mut query_result: ParameterQueryResult) -> Select<E> {
+        let relations = E::relations();
+        let group_list = query_result.group_list.clone();
+        let mut aggregate_list = query_result.aggregate_list.clone();
+        if aggregate_list.is_empty() {
+            aggregate_list.push(ColumnAggregate {
+                function: QueryAggregate::COUNT,
+                property: None,
+                alias: "count(*)".to_string(),
+            });
+        }
+
+        query_result.sort_list = vec![];
+        let mut base_query = QueryBuilder::generate(select, query_result).select_only();
+
+        for group in group_list {
+            let Some((column, _, _)) = property_column::<E>(&relations, &group.property) else {
+                continue;
+            };
+
+            let group_expr = match group.truncate {
+                Some(truncate) => Expr::cust_with_exprs(format!("date_trunc('{}', $1)", truncate.as_str()), [column]),
+                None => column,
+            };
+
+            base_query = base_query
+                .column_as(group_expr.clone(), group.alias)
+                .group_by(group_expr.clone())
+                .order_by(group_expr, Order::Asc);
+        }
+
+        for aggregate in aggregate_list {
+            let column = match &aggregate.property {
+                Some(property) => match property_column::<E>(&relations, property) {
+                    Some((column, _, _)) => column,
+                    None => continue,
+                },
+                None => Expr::col(Asterisk).into(),
+            };
+
+            let aggregate_expr = match aggregate.function {
+                QueryAggregate::COUNT => Func::count(column),
+                QueryAggregate::SUM => Func::sum(column),
+                QueryAggregate::AVG => Func::avg(column),
+                QueryAggregate::MIN => Func::min(column),
+                QueryAggregate::MAX => Func::max(column),
+            };
+
+            base_query = base_query.column_as(SimpleExpr::from(aggregate_expr), aggregate.alias);
+        }
+
+        base_query
+    }
+
+    /// Columns to select for the requested sparse fieldset with the key each one is returned
+    /// under, every column when no fields were requested. Fields are public names of the
+    /// resource's query fields and rows are keyed by them too, see [`response_key`].
+    pub fn select_columns<E: QueryResource>(fields: &[String]) -> Result<Vec<(E::Column, String)>, Vec<ErrorDetails>> {
+        let query_fields = E::query_fields();
+        if fields.is_empty() {
+            return Ok(E::Column::iter()
+                .map(|column| (column, response_key(&query_fields, column.as_str())))
+                .collect());
+        }
+
+        let mut columns: Vec<(E::Column, String)> = vec![];
+        let mut errors: Vec<ErrorDetails> = vec![];
+        for field in fields {
+            let column = query_fields
+                .iter()
+                .find(|query_field| &query_field.public_name == field)
+                .and_then(|query_field| E::Column::iter().find(|column| column.as_str() == query_field.column));
+
+            match column {
+                Some(column) => columns.push((column, field.clone())),
+                None => errors.push(ErrorDetails::bad_request(format!("Unknown field '{}'", field))),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(columns)
+    }
+
+    /// Builds the select of an already validated query, related fields join their to-one
+    /// relations and filter to-many relations through `EXISTS`, groups join every relation
+    pub fn generate<E: QueryResource>(select: Select<E>, query_result: ParameterQueryResult) -> Select<E> {
+        let relations = E::relations();
+        let paths = relation_paths(&query_result);
+        let grouped_paths: Vec<String> = query_result.group_list
+            .iter()
+            .filter_map(|group| group.property.split_once('.'))
+            .map(|(path, _)| path.to_string())
+            .collect();
+
+        let limit = query_result.limit.unwrap_or(DEFAULT_LIMIT);
+        let mut base_query = select.limit(limit);
+        if let Some(skip) = query_result.skip(limit) {
+            base_query = base_query.offset(skip);
+        }
+
+        let search = query_result.search.as_ref().and_then(search_document::<E>);
+
+        // a backward cursor reads the page before it in reverse, the rows are put back in order after
+        let mut keys = sort_keys::<E>(&relations, search.as_ref(), &query_result.sort_list);
+        if query_result.keyset.as_ref().is_some_and(|keyset| keyset.backward) {
+            keys = keys.into_iter().map(SortKey::reversed).collect();
+        }
+
+        for key in &keys {
+            let order = match key.sort {
+                QuerySort::ASC => Order::Asc,
+                QuerySort::DESC => Order::Desc,
+            };
+
+            match &key.nulls {
+                None => {
+                    base_query = base_query.order_by(key.expr.clone(), order);
+                }
+                Some(nulls) => {
+                    let null_ordering = match nulls {
+                        QueryNulls::FIRST => NullOrdering::First,
+                        QueryNulls::LAST => NullOrdering::Last,
+                    };
+
+                    QueryTrait::query(&mut base_query).order_by_expr_with_nulls(key.expr.clone(), order, null_ordering);
+                }
+            }
+        }
+
+        let mut conditions: Option<Condition> = None;
         for filter_list in query_result.filter_list {
-            let mut conditions = Condition::all();
+            let mut list_conditions: Option<Condition> = None;
             for filter in filter_list.filter_list {
-                for column in E::Column::iter() {
-                    let column_name: &str = &column.as_str();
+                let operator = filter.operator.clone();
 
-                    if filter.property != column_name.to_string() {
-                        continue;
-                    }
+                if let Some(condition) = filter_condition::<E>(&relations, filter) {
+                    list_conditions = Some(combine_conditions(list_conditions, &operator, condition));
+                }
+            }
 
-                    match filter.filter {
-                        QueryFilter::GT => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::gt(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::gt(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::gt(&column, filter.value));
-                                }
-                            }
-                        }
-                        QueryFilter::GTE => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::gte(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::gte(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::gte(&column, filter.value));
-                                }
-                            }
-                        }
-                        QueryFilter::LT => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::lt(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::lt(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::lt(&column, filter.value));
-                                }
-                            }
-                        }
-                        QueryFilter::LTE => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::lte(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::lte(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::lte(&column, filter.value));
-                                }
-                            }
-                        }
-                        QueryFilter::EQ => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::eq(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::eq(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::eq(&column, filter.value));
-                                }
-                            }
-                        }
-                        QueryFilter::NE => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::ne(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::ne(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::ne(&column, filter.value));
-                                }
-                            }
-                        }
-                        QueryFilter::LIKE => {
-                            conditions = conditions.clone().add(E::Column::contains(&column, filter.value));
-                        }
-                        QueryFilter::CURSOR => {
-                            match parse_number(&filter.value) {
-                                NumberType::Integer(value) => {
-                                    conditions = conditions.clone().add(E::Column::gte(&column, value));
-                                }
-                                NumberType::Float(value) => {
-                                    conditions = conditions.clone().add(E::Column::gte(&column, value));
-                                }
-                                NumberType::Invalid => {
-                                    conditions = conditions.clone().add(E::Column::gte(&column, filter.value));
-                                }
-                            }
-                        }
-                    }
+            if let Some(list_conditions) = list_conditions {
+                conditions = Some(combine_conditions(conditions, &filter_list.operator, list_conditions));
+            }
+        }
 
-                    break;
-                }
+        if let Some(expression_conditions) = query_result.filter_expression.and_then(|expression| expression_condition::<E>(&relations, expression)) {
+            conditions = Some(combine_conditions(conditions, &QueryOperator::AND, expression_conditions));
+        }
+
+        if let Some((document, search_query)) = search {
+            let matches = Expr::cust_with_exprs("$1 @@ $2", [document, search_query]);
+            conditions = Some(combine_conditions(conditions, &QueryOperator::AND, matches));
+        }
+
+        let keyset = query_result.keyset.and_then(|keyset| keyset_values(&keys, &keyset.values));
+        if let Some(values) = keyset {
+            conditions = Some(combine_conditions(conditions, &QueryOperator::AND, keyset_condition(&keys, &values)));
+        }
+
+        if let Some(conditions) = conditions {
+            base_query = base_query.filter(conditions);
+        }
+
+        for relation in relations {
+            // to-many relations are filtered through EXISTS and only joined to group by them
+            let joined = if relation.is_to_many() {
+                grouped_paths.contains(&relation.path)
+            } else {
+                paths.contains(&relation.path)
+            };
+            if !joined {
+                continue;
             }
 
-            base_query = base_query.clone().filter(conditions);
+            for relation_def in relation.relations {
+                base_query = base_query.join(JoinType::LeftJoin, relation_def);
+            }
         }
 
         base_query
@@ -197,8 +544,10 @@ impl QueryBuilder {
         page_count_calculation.ceil() as u64
     }
 
-    pub fn current_page(total_count: u64, remaining_count: u64, limit: u64) -> u64 {
-        let mut current_page = (total_count - remaining_count) as f64 / limit as f64;
+    /// Page the rows after the preceding ones start on, a page starting mid-way through a
+    /// page of the given limit counts as the next one
+    pub fn current_page(preceding_count: u64, limit: u64) -> u64 {
+        let current_page = preceding_count as f64 / limit as f64;
         if current_page % 1.0 != 0.0 {
             current_page.ceil() as u64
         } else {
@@ -207,18 +556,1287 @@ impl QueryBuilder {
     }
 }
 
-enum NumberType {
-    Integer(i32),
-    Float(f32),
-    Invalid,
+/// The text search vector of the searched columns and the parsed search query. The vector must
+/// stay identical to the expression of the resource's GIN index for Postgres to use it.
+fn search_document<E: EntityTrait>(search: &TextSearch) -> Option<(SimpleExpr, SimpleExpr)> {
+    let columns: Vec<E::Column> = search.columns
+        .iter()
+        .filter_map(|name| E::Column::iter().find(|column| column.as_str() == name))
+        .collect();
+    if columns.is_empty() {
+        return None;
+    }
+
+    let template = (1..=columns.len())
+        .map(|index| format!("coalesce(${}, '')", index))
+        .collect::<Vec<String>>()
+        .join(" || ' ' || ");
+    let document = Expr::cust_with_exprs(
+        format!("to_tsvector('{}', {})", SEARCH_CONFIGURATION, template),
+        columns.into_iter().map(|column| Expr::col((column.entity_name(), column)).into()),
+    );
+    let search_query = Expr::cust_with_values(
+        format!("websearch_to_tsquery('{}', $1)", SEARCH_CONFIGURATION),
+        [search.query.clone()],
+    );
+
+    Some((document, search_query))
+}
+
+/// Expression a validated sort orders by, with its direction and null placement
+struct SortKey {
+    expr: SimpleExpr,
+    column_type: ColumnType,
+    /// Whether the key can be null, related columns can always be through their left join
+    nullable: bool,
+    sort: QuerySort,
+    nulls: Option<QueryNulls>,
+}
+
+impl SortKey {
+    fn nulls_first(&self) -> bool {
+        nulls_first(&self.sort, &self.nulls)
+    }
+
+    /// The same key ordering the other way, with nulls moved to the other end
+    fn reversed(self) -> SortKey {
+        let nulls = if self.nulls_first() { QueryNulls::LAST } else { QueryNulls::FIRST };
+        let sort = match self.sort {
+            QuerySort::ASC => QuerySort::DESC,
+            QuerySort::DESC => QuerySort::ASC,
+        };
+
+        SortKey {
+            sort,
+            nulls: Some(nulls),
+            ..self
+        }
+    }
+
+    fn tied(&self, value: &Option<Value>) -> SimpleExpr {
+        match value {
+            Some(value) => Expr::expr(self.expr.clone()).eq(value.clone()),
+            None => Expr::expr(self.expr.clone()).is_null(),
+        }
+    }
+
+    /// Rows placed after the value on this key, `None` when nothing can come after it
+    fn after(&self, value: &Option<Value>) -> Option<SimpleExpr> {
+        let target = Expr::expr(self.expr.clone());
+
+        match value {
+            None if self.nulls_first() => Some(target.is_not_null()),
+            None => None,
+            Some(value) => {
+                let past = match self.sort {
+                    QuerySort::ASC => target.gt(value.clone()),
+                    QuerySort::DESC => target.lt(value.clone()),
+                };
+
+                if self.nulls_first() || !self.nullable {
+                    Some(past)
+                } else {
+                    Some(past.or(Expr::expr(self.expr.clone()).is_null()))
+                }
+            }
+        }
+    }
+}
+
+/// Postgres places nulls last when ascending and first when descending unless told otherwise
+fn nulls_first(sort: &QuerySort, nulls: &Option<QueryNulls>) -> bool {
+    match nulls {
+        Some(nulls) => *nulls == QueryNulls::FIRST,
+        None => *sort == QuerySort::DESC,
+    }
+}
+
+fn sort_keys<E: EntityTrait>(
+    relations: &[QueryRelation],
+    search: Option<&(SimpleExpr, SimpleExpr)>,
+    sort_list: &[ColumnSort],
+) -> Vec<SortKey> {
+    sort_list
+        .iter()
+        .filter_map(|column_sort| {
+            let (expr, column_type, nullable) = if column_sort.property == RELEVANCE {
+                let (document, search_query) = search?.clone();
+                (Expr::cust_with_exprs("ts_rank($1, $2)", [document, search_query]), ColumnType::Float, false)
+            } else {
+                let (column, column_type, relation) = property_column::<E>(relations, &column_sort.property)?;
+                let nullable = relation.is_some() || E::Column::iter()
+                    .find(|column| column.as_str() == column_sort.property)
+                    .is_none_or(|column| column.def().is_null());
+                (column, column_type, nullable)
+            };
+
+            Some(SortKey {
+                expr,
+                column_type,
+                nullable,
+                sort: column_sort.sort.clone(),
+                nulls: column_sort.nulls.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Names a validated sort with the null placement it resolves to, a cursor only applies to
+/// the sort it was issued for
+fn sort_signature(sort_list: &[ColumnSort]) -> String {
+    sort_list
+        .iter()
+        .map(|column_sort| {
+            let nulls = if nulls_first(&column_sort.sort, &column_sort.nulls) { QueryNulls::FIRST } else { QueryNulls::LAST };
+
+            format!("{}:{}:{}", column_sort.property, column_sort.sort.as_str(), nulls.as_str())
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Row count of the select as estimated by the Postgres planner from the table statistics,
+/// far cheaper than counting on large tables but only as accurate as the last `ANALYZE`
+async fn estimate_count<E: EntityTrait, C: ConnectionTrait>(db: &C, mut select: Select<E>) -> Result<u64, Vec<ErrorDetails>> {
+    QueryTrait::query(&mut select).reset_limit().reset_offset().clear_order_by();
+    let plan = query_plan(db, &select).await?;
+
+    Ok(plan
+        .get("Plan Rows")
+        .and_then(JsonValue::as_f64)
+        .map_or(0, |rows| rows.round() as u64))
+}
+
+/// Rows before the page, taken from the cursor position when paging by cursor. A backward page
+/// without more rows before it is the first page, whatever the cursor says.
+fn preceding_count(keyset: Option<&Cursor>, skip: Option<u64>, page_size: u64, has_more: bool) -> u64 {
+    match keyset {
+        Some(keyset) if keyset.backward && !has_more => 0,
+        Some(keyset) if keyset.backward => keyset.position.saturating_sub(page_size),
+        Some(keyset) => keyset.position + 1,
+        None => skip.unwrap_or(0),
+    }
+}
+
+/// Whether the page shows where the list ends, so the rows before it and on it are the total.
+/// Positions of a cursor are as of when it was issued, only an offset gives an exact total, and
+/// an empty page after an offset may lie anywhere past the end.
+fn ends_list(keyset: Option<&Cursor>, skip: Option<u64>, page_size: u64, has_more: bool) -> bool {
+    !has_more && keyset.is_none() && (page_size > 0 || skip.unwrap_or(0) == 0)
+}
+
+fn cursor_alias(index: usize) -> String {
+    format!("{}{}", CURSOR_KEY, index)
+}
+
+/// Binds the sort key values of a cursor as the types of their keys, `None` entries are nulls
+fn keyset_values(keys: &[SortKey], values: &[JsonValue]) -> Option<Vec<Option<Value>>> {
+    if keys.len() != values.len() {
+        return None;
+    }
+
+    keys.iter()
+        .zip(values)
+        .map(|(key, value)| {
+            let input = match value {
+                JsonValue::Null => return Some(None),
+                JsonValue::String(text) => text.clone(),
+                JsonValue::Number(number) => number.to_string(),
+                JsonValue::Bool(boolean) => boolean.to_string(),
+                _ => return None,
+            };
+
+            column_value(&key.column_type, &input).ok().map(Some)
+        })
+        .collect()
+}
+
+/// Rows after the cursor values in the order of the keys, for every key the rows tied on the
+/// keys before it and placed after the value on this one, e.g.
+/// `(a > 1 OR a IS NULL) OR (a = 1 AND b < 2)` for `asc(a),desc(b)`
+fn keyset_condition(keys: &[SortKey], values: &[Option<Value>]) -> Condition {
+    let mut condition = Condition::any();
+    for (index, (key, value)) in keys.iter().zip(values).enumerate() {
+        let Some(after) = key.after(value) else {
+            continue;
+        };
+
+        let tied = keys.iter()
+            .zip(values)
+            .take(index)
+            .fold(Condition::all(), |tied, (key, value)| tied.add(key.tied(value)));
+        condition = condition.add(tied.add(after));
+    }
+
+    if condition.is_empty() {
+        return Condition::all().add(Expr::value(false));
+    }
+
+    condition
+}
+
+/// Rows of a validated query with their includes, each carrying its sort key values under
+/// `CURSOR_KEY` aliases to build cursors from
+async fn find_rows<E: QueryResource, C: ConnectionTrait>(
+    db: &C,
+    query_result: ParameterQueryResult,
+) -> Result<Vec<JsonValue>, Vec<ErrorDetails>> {
+    let columns = QueryBuilder::select_columns::<E>(&query_result.fields)?;
+    let include = query_result.include.clone();
+    let relations = E::relations();
+    let search = query_result.search.as_ref().and_then(search_document::<E>);
+    let cursor_keys = sort_keys::<E>(&relations, search.as_ref(), &query_result.sort_list);
+
+    let mut base_query = QueryBuilder::generate(E::find(), query_result).select_only();
+    for (column, key) in columns {
+        base_query = base_query.column_as(column, key);
+    }
+    for (index, key) in cursor_keys.into_iter().enumerate() {
+        base_query = base_query.column_as(key.expr, cursor_alias(index));
+    }
+
+    check_cost(db, &base_query).await?;
+
+    let mut rows = match base_query
+        .into_json()
+        .all(db)
+        .await {
+        Ok(result) => {
+            result
+        }
+        Err(error) => {
+            return Err(database_error(error));
+        }
+    };
+
+    for path in include {
+        let Some(relation) = relations.iter().find(|relation| relation.path == path) else {
+            continue;
+        };
+
+        load_include::<E, C>(db, relation, &mut rows).await?;
+    }
+
+    Ok(rows)
+}
+
+/// Key a column of the resource is returned under, the public name of its query field and the
+/// camelCase column name for columns that aren't query fields
+fn response_key(query_fields: &[QueryField], column: &str) -> String {
+    query_fields
+        .iter()
+        .find(|query_field| query_field.column == column)
+        .map_or_else(|| camel_case(column), |query_field| query_field.public_name.clone())
 }
 
-fn parse_number(input: &String) -> NumberType {
-    if let Ok(integer) = input.parse() {
-        return NumberType::Integer(integer);
-    } else if let Ok(float) = input.parse() {
-        return NumberType::Float(float);
+/// Column of the resource the related rows of an include are matched on
+fn parent_key(relation: &QueryRelation) -> Option<String> {
+    let first = relation.relations.first()?;
+    let mut columns = first.from_col.clone().into_iter();
+
+    match (columns.next(), columns.next()) {
+        (Some(column), None) => Some(column.to_string()),
+        _ => None,
     }
+}
+
+async fn load_include<E: QueryResource, C: ConnectionTrait>(
+    db: &C,
+    relation: &QueryRelation,
+    rows: &mut [JsonValue],
+) -> Result<(), Vec<ErrorDetails>> {
+    let Some(column) = parent_key(relation) else {
+        return Ok(());
+    };
+    let Some(key_column) = E::Column::iter().find(|key_column| key_column.as_str() == column) else {
+        return Ok(());
+    };
+    let key = response_key(&E::query_fields(), &column);
+
+    let key_type = key_column.def().get_column_type().clone();
+    let keys: Vec<Value> = rows
+        .iter()
+        .filter_map(|row| row.get(&key))
+        .filter_map(|value| match value {
+            JsonValue::Number(number) => Some(number.to_string()),
+            JsonValue::String(text) => Some(text.clone()),
+            _ => None,
+        })
+        .map(|value| coerce_value(&key_type, &value))
+        .collect();
+
+    let related = match include_query(relation, keys) {
+        Some(query) => {
+            JsonValue::find_by_statement(db.get_database_backend().build(&query))
+                .all(db)
+                .await
+                .map_err(database_error)?
+        }
+        None => vec![],
+    };
+
+    embed_included(rows, &key, &relation.path, relation.is_to_many(), related);
+
+    Ok(())
+}
+
+/// One query loading the related rows of every parent key, each row carries the key it
+/// belongs to as `INCLUDE_KEY`
+fn include_query(relation: &QueryRelation, keys: Vec<Value>) -> Option<SelectStatement> {
+    if keys.is_empty() {
+        return None;
+    }
+
+    let mut relations = relation.relations.iter();
+    let first = relations.next()?;
+    let parent_column = first.to_col.clone().into_iter().next()?;
+    let parent_column = Expr::col((table_iden(&first.to_tbl), parent_column));
+
+    let mut query = Query::select();
+    query
+        .distinct()
+        .column((Alias::new(relation.table()), Asterisk))
+        .expr_as(parent_column.clone(), Alias::new(INCLUDE_KEY))
+        .from(first.to_tbl.clone());
+    for relation in relations {
+        query.join(JoinType::InnerJoin, relation.to_tbl.clone(), join_condition(relation));
+    }
+    query.and_where(parent_column.is_in(keys));
+
+    Some(query)
+}
+
+/// Adds the related rows to their parents under the relation path, as a list for to-many
+/// relations and as a single row or null for to-one relations
+fn embed_included(rows: &mut [JsonValue], key: &str, path: &str, to_many: bool, related: Vec<JsonValue>) {
+    let mut grouped: HashMap<String, Vec<JsonValue>> = HashMap::new();
+    for mut related_row in related {
+        let Some(parent) = related_row.as_object_mut().and_then(|object| object.remove(INCLUDE_KEY)) else {
+            continue;
+        };
+
+        grouped.entry(parent.to_string()).or_default().push(related_row);
+    }
+
+    for row in rows.iter_mut() {
+        let included = row.get(key)
+            .and_then(|parent| grouped.get(&parent.to_string()))
+            .cloned()
+            .unwrap_or_default();
+
+        if let Some(object) = row.as_object_mut() {
+            let value = if to_many {
+                JsonValue::Array(included)
+            } else {
+                included.into_iter().next().unwrap_or(JsonValue::Null)
+            };
+
+            object.insert(path.to_string(), value);
+        }
+    }
+}
+
+fn is_date_type(column_type: &ColumnType) -> bool {
+    matches!(column_type, ColumnType::Date | ColumnType::DateTime | ColumnType::Timestamp | ColumnType::TimestampWithTimeZone)
+}
+
+fn is_numeric_type(column_type: &ColumnType) -> bool {
+    matches!(
+        column_type,
+        ColumnType::TinyInteger | ColumnType::SmallInteger | ColumnType::Integer | ColumnType::BigInteger
+            | ColumnType::TinyUnsigned | ColumnType::SmallUnsigned | ColumnType::Unsigned | ColumnType::BigUnsigned
+            | ColumnType::Float | ColumnType::Double | ColumnType::Decimal(_) | ColumnType::Money(_)
+    )
+}
+
+/// A filter or sort property resolved against the resource fields and relations
+struct ResolvedProperty<'a> {
+    field: &'a QueryField,
+    /// Column name, prefixed with the relation path for related fields
+    property: String,
+    column_type: Option<ColumnType>,
+    to_many: bool,
+}
+
+fn resolve_property<'a, E: EntityTrait>(
+    query_fields: &'a [QueryField],
+    relations: &'a [QueryRelation],
+    property: &str,
+) -> Option<ResolvedProperty<'a>> {
+    match property.split_once('.') {
+        None => {
+            let field = query_fields.iter().find(|field| field.public_name == property)?;
+            let column_type = E::Column::iter()
+                .find(|column| column.as_str() == field.column)
+                .map(|column| column.def().get_column_type().clone());
+
+            Some(ResolvedProperty {
+                field,
+                property: field.column.clone(),
+                column_type,
+                to_many: false,
+            })
+        }
+        Some((path, name)) => {
+            let relation = relations.iter().find(|relation| relation.path == path)?;
+            let field = relation.fields.iter().find(|field| field.public_name == name)?;
+
+            Some(ResolvedProperty {
+                field,
+                property: format!("{}.{}", relation.path, field.column),
+                column_type: relation.column_type(&field.column).cloned(),
+                to_many: relation.is_to_many(),
+            })
+        }
+    }
+}
+
+fn resolve_filter<E: EntityTrait>(
+    query_fields: &[QueryField],
+    relations: &[QueryRelation],
+    filter: &mut ColumnFilter,
+    now: DateTime<Utc>,
+) -> Result<(), ErrorDetails> {
+    let Some(resolved) = resolve_property::<E>(query_fields, relations, &filter.property) else {
+        return Err(ErrorDetails::bad_request(format!("Unknown filter property '{}'", filter.property)));
+    };
+
+    if !resolved.field.filters.contains(&filter.filter) {
+        return Err(ErrorDetails::bad_request(format!(
+            "Filter '{}' is not allowed on '{}'",
+            filter.filter.as_str(),
+            filter.property
+        )));
+    }
+
+    if let Some(column_type) = &resolved.column_type {
+        if let Err(expected) = coerce_filter_value(column_type, filter, now) {
+            return Err(ErrorDetails::bad_request(format!(
+                "Invalid value '{}' for '{}', expected {}",
+                filter.value,
+                filter.property,
+                expected
+            )));
+        }
+    }
+
+    filter.property = resolved.property;
+
+    Ok(())
+}
 
-    NumberType::Invalid
+/// Resolves relative dates in the filter value and makes sure it parses as the type of its
+/// column, text matching filters always compare as text and null filters have no value
+fn coerce_filter_value(column_type: &ColumnType, filter: &mut ColumnFilter, now: DateTime<Utc>) -> Result<(), &'static str> {
+    match filter.filter {
+        QueryFilter::IN | QueryFilter::NIN | QueryFilter::BETWEEN => {
+            filter.value = filter.value
+                .split(',')
+                .map(|item| resolve_relative_value(column_type, item, now))
+                .collect::<Vec<String>>()
+                .join(",");
+
+            column_values(column_type, &filter.value).map(|_| ())
+        }
+        QueryFilter::GT | QueryFilter::GTE | QueryFilter::LT | QueryFilter::LTE | QueryFilter::EQ | QueryFilter::NE | QueryFilter::CURSOR => {
+            filter.value = resolve_relative_value(column_type, &filter.value, now);
+
+            column_value(column_type, &filter.value).map(|_| ())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn resolve_expression<E: EntityTrait>(
+    query_fields: &[QueryField],
+    relations: &[QueryRelation],
+    expression: &mut FilterExpression,
+    now: DateTime<Utc>,
+    errors: &mut Vec<ErrorDetails>,
+) {
+    match expression {
+        FilterExpression::And(expressions) | FilterExpression::Or(expressions) => {
+            for expression in expressions {
+                resolve_expression::<E>(query_fields, relations, expression, now, errors);
+            }
+        }
+        FilterExpression::Not(expression) => {
+            resolve_expression::<E>(query_fields, relations, expression, now, errors);
+        }
+        FilterExpression::Filter(filter) => {
+            if let Err(error) = resolve_filter::<E>(query_fields, relations, filter, now) {
+                errors.push(error);
+            }
+        }
+    }
+}
+
+/// Column expression and type of a validated property, related columns are qualified with
+/// the table of their relation
+fn property_column<'a, E: EntityTrait>(
+    relations: &'a [QueryRelation],
+    property: &str,
+) -> Option<(SimpleExpr, ColumnType, Option<&'a QueryRelation>)> {
+    match property.split_once('.') {
+        None => {
+            let column = E::Column::iter().find(|column| column.as_str() == property)?;
+            let column_type = column.def().get_column_type().clone();
+
+            Some((Expr::col((column.entity_name(), column)).into(), column_type, None))
+        }
+        Some((path, name)) => {
+            let relation = relations.iter().find(|relation| relation.path == path)?;
+            let column_type = relation.column_type(name)?.clone();
+
+            Some((Expr::col((Alias::new(relation.table()), Alias::new(name))).into(), column_type, Some(relation)))
+        }
+    }
+}
+
+fn filter_condition<E: EntityTrait>(relations: &[QueryRelation], filter: ColumnFilter) -> Option<SimpleExpr> {
+    let (column, column_type, relation) = property_column::<E>(relations, &filter.property)?;
+    let target = || Expr::expr(column.clone());
+
+    let condition = match filter.filter {
+        QueryFilter::GT => {
+            target().gt(coerce_value(&column_type, &filter.value))
+        }
+        QueryFilter::GTE => {
+            target().gte(coerce_value(&column_type, &filter.value))
+        }
+        QueryFilter::LT => {
+            target().lt(coerce_value(&column_type, &filter.value))
+        }
+        QueryFilter::LTE => {
+            target().lte(coerce_value(&column_type, &filter.value))
+        }
+        QueryFilter::EQ => {
+            target().eq(coerce_value(&column_type, &filter.value))
+        }
+        QueryFilter::NE => {
+            target().ne(coerce_value(&column_type, &filter.value))
+        }
+        QueryFilter::LIKE => {
+            let pattern = format!("%{}%", escape_like(&filter.value));
+            target().like(pattern)
+        }
+        QueryFilter::ILIKE => {
+            let pattern = format!("%{}%", escape_like(&filter.value));
+            target().ilike(pattern)
+        }
+        QueryFilter::STARTSWITH => {
+            let pattern = format!("{}%", escape_like(&filter.value));
+            target().like(pattern)
+        }
+        QueryFilter::ISTARTSWITH => {
+            let pattern = format!("{}%", escape_like(&filter.value));
+            target().ilike(pattern)
+        }
+        QueryFilter::ENDSWITH => {
+            let pattern = format!("%{}", escape_like(&filter.value));
+            target().like(pattern)
+        }
+        QueryFilter::IENDSWITH => {
+            let pattern = format!("%{}", escape_like(&filter.value));
+            target().ilike(pattern)
+        }
+        QueryFilter::IEQ => {
+            Expr::expr(Func::lower(column.clone()))
+                .eq(Func::lower(Expr::val(filter.value)))
+        }
+        QueryFilter::IN => {
+            target().is_in(coerce_list(&column_type, &filter.value))
+        }
+        QueryFilter::NIN => {
+            target().is_not_in(coerce_list(&column_type, &filter.value))
+        }
+        QueryFilter::BETWEEN => {
+            let range = coerce_list(&column_type, &filter.value);
+            target().between(range[0].clone(), range[1].clone())
+        }
+        QueryFilter::NULL => {
+            target().is_null()
+        }
+        QueryFilter::NOTNULL => {
+            target().is_not_null()
+        }
+        QueryFilter::CURSOR => {
+            target().gte(coerce_value(&column_type, &filter.value))
+        }
+    };
+
+    match relation {
+        Some(relation) if relation.is_to_many() => Some(exists_condition(relation, condition)),
+        _ => Some(condition),
+    }
+}
+
+/// Matches resource rows with at least one related row meeting the condition, so to-many
+/// relations don't repeat resource rows the way a join would
+fn exists_condition(relation: &QueryRelation, condition: SimpleExpr) -> SimpleExpr {
+    let mut relations = relation.relations.iter();
+    let Some(first) = relations.next() else {
+        return condition;
+    };
+
+    let mut query = Query::select();
+    query.expr(Expr::val(1)).from(first.to_tbl.clone());
+    for relation in relations {
+        query.join(JoinType::InnerJoin, relation.to_tbl.clone(), join_condition(relation));
+    }
+    query.cond_where(join_condition(first).add(condition));
+
+    Expr::exists(query)
+}
+
+fn join_condition(relation: &RelationDef) -> Condition {
+    let from_table = table_iden(&relation.from_tbl);
+    let to_table = table_iden(&relation.to_tbl);
+
+    relation.from_col
+        .clone()
+        .into_iter()
+        .zip(relation.to_col.clone())
+        .fold(Condition::all(), |condition, (from_column, to_column)| {
+            condition.add(Expr::col((from_table.clone(), from_column)).equals((to_table.clone(), to_column)))
+        })
+}
+
+fn table_iden(table_ref: &TableRef) -> DynIden {
+    match table_ref {
+        TableRef::Table(table)
+        | TableRef::SchemaTable(_, table)
+        | TableRef::DatabaseSchemaTable(_, _, table)
+        | TableRef::TableAlias(table, _)
+        | TableRef::SchemaTableAlias(_, table, _)
+        | TableRef::DatabaseSchemaTableAlias(_, _, table, _)
+        | TableRef::SubQuery(_, table)
+        | TableRef::ValuesList(_, table)
+        | TableRef::FunctionCall(_, table) => table.clone(),
+    }
+}
+
+/// Relation paths used by the filters, sorts, groups and aggregates of a validated query
+fn relation_paths(query_result: &ParameterQueryResult) -> Vec<String> {
+    fn expression_properties<'a>(expression: &'a FilterExpression, properties: &mut Vec<&'a str>) {
+        match expression {
+            FilterExpression::And(expressions) | FilterExpression::Or(expressions) => {
+                for expression in expressions {
+                    expression_properties(expression, properties);
+                }
+            }
+            FilterExpression::Not(expression) => expression_properties(expression, properties),
+            FilterExpression::Filter(filter) => properties.push(&filter.property),
+        }
+    }
+
+    let mut properties: Vec<&str> = query_result.filter_list
+        .iter()
+        .flat_map(|filter_list| &filter_list.filter_list)
+        .map(|filter| filter.property.as_str())
+        .chain(query_result.sort_list.iter().map(|column_sort| column_sort.property.as_str()))
+        .chain(query_result.group_list.iter().map(|group| group.property.as_str()))
+        .chain(query_result.aggregate_list.iter().filter_map(|aggregate| aggregate.property.as_deref()))
+        .collect();
+    if let Some(filter_expression) = &query_result.filter_expression {
+        expression_properties(filter_expression, &mut properties);
+    }
+
+    let mut paths: Vec<String> = vec![];
+    for (path, _) in properties.into_iter().filter_map(|property| property.split_once('.')) {
+        if !paths.iter().any(|known| known == path) {
+            paths.push(path.to_string());
+        }
+    }
+
+    paths
+}
+
+fn expression_condition<E: EntityTrait>(relations: &[QueryRelation], expression: FilterExpression) -> Option<Condition> {
+    let condition = match expression {
+        FilterExpression::And(expressions) => {
+            expressions
+                .into_iter()
+                .filter_map(|expression| expression_condition::<E>(relations, expression))
+                .fold(Condition::all(), |condition, expression| condition.add(expression))
+        }
+        FilterExpression::Or(expressions) => {
+            expressions
+                .into_iter()
+                .filter_map(|expression| expression_condition::<E>(relations, expression))
+                .fold(Condition::any(), |condition, expression| condition.add(expression))
+        }
+        FilterExpression::Not(expression) => {
+            expression_condition::<E>(relations, *expression)?.not()
+        }
+        FilterExpression::Filter(filter) => {
+            Condition::all().add(filter_condition::<E>(relations, filter)?)
+        }
+    };
+
+    if condition.is_empty() {
+        return None;
+    }
+
+    Some(condition)
+}
+
+/// Joins the conditions built so far with the next one, using the operator given with it
+fn combine_conditions<C>(conditions: Option<Condition>, operator: &QueryOperator, condition: C) -> Condition
+    where
+        C: Into<ConditionExpression>,
+{
+    match (conditions, operator) {
+        (None, _) => Condition::all().add(condition),
+        (Some(conditions), QueryOperator::AND) => Condition::all().add(conditions).add(condition),
+        (Some(conditions), QueryOperator::OR) => Condition::any().add(conditions).add(condition),
+    }
+}
+
+/// Value bound for a filter, falls back to text when the query was not validated against the column type
+fn coerce_value(column_type: &ColumnType, input: &str) -> Value {
+    column_value(column_type, input).unwrap_or_else(|_| Value::from(input))
+}
+
+/// Escapes the LIKE wildcards in user input so `%` and `_` are matched literally,
+/// relies on backslash being the default LIKE/ILIKE escape character in Postgres
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn coerce_list(column_type: &ColumnType, input: &str) -> Vec<Value> {
+    input
+        .split(',')
+        .map(|item| coerce_value(column_type, item))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{DbBackend, EntityTrait, JsonValue, QuerySelect, QueryTrait, Value};
+    use sea_orm::sea_query::PostgresQueryBuilder;
+
+    use chrono::{TimeZone, Utc};
+    use http::StatusCode;
+    use serde_json::json;
+
+    use crate::database::cursor::{cursor_secret, Cursor};
+    use crate::database::query_builder::{embed_included, ends_list, include_query, preceding_count, QueryBuilder};
+    use crate::database::query_resource::QueryResource;
+    use crate::global::parameter_query_builder::ParameterQueryResult;
+    use crate::users::user::Entity;
+
+    fn generate_sql(query: &str) -> String {
+        let query_result = ParameterQueryResult::build_query_result(Some(query.to_string())).unwrap();
+
+        QueryBuilder::generate(Entity::find(), query_result)
+            .build(DbBackend::Postgres)
+            .to_string()
+    }
+
+    #[test]
+    fn given_in_filter_should_generate_in_condition() {
+        let sql = generate_sql("email[in]=a@x.io,b@x.io");
+
+        assert!(sql.contains(r#""user_base"."email" IN ('a@x.io', 'b@x.io')"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_not_in_filter_should_generate_not_in_condition() {
+        let sql = generate_sql("id[nin]=1,2,3");
+
+        assert!(sql.contains(r#""user_base"."id" NOT IN (1, 2, 3)"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_null_filters_should_generate_null_conditions() {
+        let sql = generate_sql("phone[null]&updated_on[notnull]");
+
+        assert!(sql.contains(r#""user_base"."phone" IS NULL"#), "{}", sql);
+        assert!(sql.contains(r#""user_base"."updated_on" IS NOT NULL"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_between_filter_should_generate_inclusive_range() {
+        let sql = generate_sql("created_on[between]=2024-01-01,2024-02-01");
+
+        assert!(sql.contains(r#""user_base"."created_on" BETWEEN '2024-01-01 00:00:00 +00:00' AND '2024-02-01 00:00:00 +00:00'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_like_filter_should_escape_wildcards() {
+        let sql = generate_sql("email[like]=50%25_off");
+
+        assert!(sql.contains(r#""user_base"."email" LIKE E'%50\\%\\_off%'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_case_insensitive_prefix_filter_should_generate_ilike() {
+        let sql = generate_sql("first_name[istarts_with]=ann");
+
+        assert!(sql.contains(r#""user_base"."first_name" ILIKE 'ann%'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_ends_with_filter_should_generate_anchored_like() {
+        let sql = generate_sql("email[ends_with]=@internal.io");
+
+        assert!(sql.contains(r#""user_base"."email" LIKE '%@internal.io'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_case_insensitive_equal_filter_should_compare_lowercase() {
+        let sql = generate_sql("email[ieq]=User@Internal.io");
+
+        assert!(sql.contains(r#"LOWER("user_base"."email") = LOWER('User@Internal.io')"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_grouped_filter_expression_should_generate_nested_conditions() {
+        let sql = generate_sql("filter=(first_name%3DAnn+or+last_name%3DLee)+and+not+phone%5Bnull%5D");
+
+        assert!(sql.contains(
+            r#"WHERE ("user_base"."first_name" = 'Ann' OR "user_base"."last_name" = 'Lee') AND (NOT ("user_base"."phone" IS NULL))"#
+        ), "{}", sql);
+    }
+
+    #[test]
+    fn given_or_operator_for_list_should_generate_or_condition() {
+        let sql = generate_sql("first_name=Ann&[or]last_name=Lee");
+
+        assert!(sql.contains(r#"WHERE "user_base"."first_name" = 'Ann' OR "user_base"."last_name" = 'Lee'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_sorts_should_generate_order_by_in_requested_order() {
+        let sql = generate_sql("sort_by=desc(created_on),asc_nulls_first(last_name),asc(id)");
+
+        assert!(sql.contains(
+            r#"ORDER BY "user_base"."created_on" DESC, "user_base"."last_name" ASC NULLS FIRST, "user_base"."id" ASC"#
+        ), "{}", sql);
+    }
+
+    #[test]
+    fn given_fields_should_select_only_those_columns_under_their_public_names() {
+        let columns = QueryBuilder::select_columns::<Entity>(&["id".to_string(), "firstName".to_string()]).unwrap();
+        let sql = columns
+            .into_iter()
+            .fold(Entity::find().select_only(), |select, (column, key)| select.column_as(column, key))
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert_eq!(sql, r#"SELECT "user_base"."id" AS "id", "user_base"."first_name" AS "firstName" FROM "user_base""#);
+    }
+
+    #[test]
+    fn given_no_fields_should_select_every_column() {
+        let columns = QueryBuilder::select_columns::<Entity>(&[]).unwrap();
+        let keys: Vec<String> = columns.into_iter().map(|(_, key)| key).collect();
+
+        assert_eq!(keys, ["id", "firstName", "lastName", "email", "phone", "createdOn", "updatedOn"]);
+    }
+
+    #[test]
+    fn given_unknown_field_should_return_error() {
+        let result = QueryBuilder::select_columns::<Entity>(&["id".to_string(), "password".to_string(), "first_name".to_string()]);
+        let messages: Vec<String> = result.unwrap_err().into_iter().map(|error| error.message).collect();
+
+        assert_eq!(messages, vec!["Unknown field 'password'", "Unknown field 'first_name'"]);
+    }
+
+    #[test]
+    fn given_page_should_generate_offset() {
+        let sql = generate_sql("page=3&limit=20");
+
+        assert!(sql.ends_with("LIMIT 20 OFFSET 40"), "{}", sql);
+    }
+
+    #[test]
+    fn given_page_overflowing_skip_should_return_error() {
+        for query in ["page=18446744073709551615&limit=20", "page=461168601842738792&limit=20"] {
+            let query_result = ParameterQueryResult::build_query_result(Some(query.to_string())).unwrap();
+            let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+            assert_eq!(errors[0].status_code, StatusCode::BAD_REQUEST, "{}", query);
+            assert!(errors[0].message.starts_with("Page ") && errors[0].message.ends_with(" is out of range for a limit of 20"), "{}", query);
+        }
+    }
+
+    #[test]
+    fn given_no_limit_should_use_resource_default() {
+        let query_result = ParameterQueryResult::build_query_result(None).unwrap();
+        let query_result = QueryBuilder::validate::<Entity>(query_result).unwrap();
+
+        assert_eq!(query_result.limit, Some(Entity::default_limit()));
+    }
+
+    #[test]
+    fn given_limit_above_resource_maximum_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some("limit=1000".to_string())).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors[0].status_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn given_public_names_should_map_filters_and_sorts_to_columns() {
+        let query_result = ParameterQueryResult::build_query_result(Some(
+            "firstName[istarts_with]=ann&filter=lastName%5Bnull%5D&sort_by=desc(createdOn)".to_string()
+        )).unwrap();
+        let query_result = QueryBuilder::validate::<Entity>(query_result).unwrap();
+        let sql = QueryBuilder::generate(Entity::find(), query_result)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(sql.contains(r#""user_base"."first_name" ILIKE 'ann%'"#), "{}", sql);
+        assert!(sql.contains(r#""user_base"."last_name" IS NULL"#), "{}", sql);
+        assert!(sql.contains(r#"ORDER BY "user_base"."created_on" DESC"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_unknown_property_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some(
+            "password=secret&filter=first_name%3DAnn&sort_by=asc(unknown)".to_string()
+        )).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn given_filter_not_allowed_on_field_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some("createdOn[like]=2024".to_string())).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors[0].message, "Filter 'like' is not allowed on 'createdOn'");
+    }
+
+    #[test]
+    fn given_sort_on_unsortable_field_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some("sort_by=asc(phone)".to_string())).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors[0].message, "Sorting on 'phone' is not allowed");
+    }
+
+    #[test]
+    fn given_value_not_matching_column_type_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some(
+            "id[gt]=3000000000&createdOn[between]=2024-01-01,soon&filter=id%3Dabc".to_string()
+        )).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].message, "Invalid value '3000000000' for 'id', expected an integer");
+        assert_eq!(errors[1].message, "Invalid value '2024-01-01,soon' for 'createdOn', expected a date, an RFC 3339 timestamp or a relative date");
+    }
+
+    #[test]
+    fn given_out_of_range_relative_date_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some("createdOn[gte]=now-999999999999999d".to_string())).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors[0].status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(errors[0].message, "Invalid value 'now-999999999999999d' for 'createdOn', expected a date, an RFC 3339 timestamp or a relative date");
+    }
+
+    #[test]
+    fn given_text_matching_filter_should_not_check_column_type() {
+        let query_result = ParameterQueryResult::build_query_result(Some("phone=5555&email[like]=100%25".to_string())).unwrap();
+
+        assert!(QueryBuilder::validate::<Entity>(query_result).is_ok());
+    }
+
+    #[test]
+    fn given_text_column_should_bind_number_like_value_as_text() {
+        let sql = generate_sql("phone=5555");
+
+        assert!(sql.contains(r#""user_base"."phone" = '5555'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_timestamp_column_should_bind_timestamp_value() {
+        let sql = generate_sql("created_on[gte]=2024-01-01T10:00:00Z");
+
+        assert!(sql.contains(r#""user_base"."created_on" >= '2024-01-01 10:00:00 +00:00'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_relative_dates_should_resolve_against_request_clock() {
+        let mut query_result = ParameterQueryResult::build_query_result(Some(
+            "createdOn[gte]=now-7d&filter=updatedOn%5Bgte%5D%3Dstart_of_month&createdOn[between]=today,today%2B1d".to_string()
+        )).unwrap();
+        query_result.requested_at = Utc.with_ymd_and_hms(2024, 3, 14, 15, 9, 26).unwrap();
+        let query_result = QueryBuilder::validate::<Entity>(query_result).unwrap();
+        let sql = QueryBuilder::generate(Entity::find(), query_result)
+            .build(DbBackend::Postgres)
+            .to_string();
+
+        assert!(sql.contains(r#""user_base"."created_on" >= '2024-03-07 15:09:26 +00:00'"#), "{}", sql);
+        assert!(sql.contains(r#""user_base"."created_on" BETWEEN '2024-03-14 00:00:00 +00:00' AND '2024-03-15 00:00:00 +00:00'"#), "{}", sql);
+        assert!(sql.contains(r#""user_base"."updated_on" >= '2024-03-01 00:00:00 +00:00'"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_search_should_match_and_rank_on_search_columns() {
+        let query_result = ParameterQueryResult::build_query_result(Some("q=ann+smith&sort_by=desc(relevance),asc(id)".to_string())).unwrap();
+        let query_result = QueryBuilder::validate::<Entity>(query_result).unwrap();
+        let sql = QueryBuilder::generate(Entity::find(), query_result)
+            .build(DbBackend::Postgres)
+            .to_string();
+        let document = r#"to_tsvector('simple', coalesce("user_base"."first_name", '') || ' ' || coalesce("user_base"."last_name", '') || ' ' || coalesce("user_base"."email", ''))"#;
+
+        assert!(sql.contains(&format!("WHERE {} @@ websearch_to_tsquery('simple', 'ann smith')", document)), "{}", sql);
+        assert!(sql.contains(&format!("ORDER BY ts_rank({}, websearch_to_tsquery('simple', 'ann smith')) DESC, \"user_base\".\"id\" ASC", document)), "{}", sql);
+    }
+
+    #[test]
+    fn given_relevance_sort_without_search_should_return_error() {
+        let query_result = ParameterQueryResult::build_query_result(Some("sort_by=desc(relevance)".to_string())).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+
+        assert_eq!(errors[0].message, "Sorting on 'relevance' requires a 'q' search");
+    }
+
+    fn validated_sql(query: &str) -> String {
+        let query_result = ParameterQueryResult::build_query_result(Some(query.to_string())).unwrap();
+        let query_result = QueryBuilder::validate::<Entity>(query_result).unwrap();
+
+        QueryBuilder::generate(Entity::find(), query_result)
+            .build(DbBackend::Postgres)
+            .to_string()
+    }
+
+    #[test]
+    fn given_to_one_relation_filter_and_sort_should_left_join() {
+        let sql = validated_sql("internalUser.createdOn[gt]=2024-01-01&sort_by=desc(internalUser.createdOn)");
+
+        assert!(sql.contains(r#"LEFT JOIN "internal_user" ON "user_base"."id" = "internal_user"."user_id""#), "{}", sql);
+        assert!(sql.contains(r#""internal_user"."created_on" > '2024-01-01 00:00:00 +00:00'"#), "{}", sql);
+        assert!(sql.contains(r#"ORDER BY "internal_user"."created_on" DESC"#), "{}", sql);
+        assert_eq!(sql.matches("JOIN").count(), 1, "{}", sql);
+    }
+
+    #[test]
+    fn given_to_many_relation_filter_should_use_exists_without_join() {
+        let sql = validated_sql("roles.name[eq]=TOP_LEVEL");
+
+        assert!(sql.contains(concat!(
+            r#"EXISTS(SELECT 1 FROM "internal_user" "#,
+            r#"INNER JOIN "authorization_role_internal_user" ON "internal_user"."id" = "authorization_role_internal_user"."internal_user_id" "#,
+            r#"INNER JOIN "authorization_role" ON "authorization_role_internal_user"."role_id" = "authorization_role"."id" "#,
+            r#"WHERE "user_base"."id" = "internal_user"."user_id" AND "authorization_role"."name" = 'TOP_LEVEL')"#,
+        )), "{}", sql);
+        assert!(!sql.contains("LEFT JOIN"), "{}", sql);
+    }
+
+    #[test]
+    fn given_negated_to_many_relation_filter_should_use_not_exists() {
+        let sql = validated_sql("filter=not%20roles.name%5Beq%5D%3DTOP_LEVEL");
+
+        assert!(sql.contains("WHERE NOT (EXISTS(SELECT 1 FROM \"internal_user\""), "{}", sql);
+    }
+
+    #[test]
+    fn given_invalid_relation_properties_should_return_errors() {
+        let query_result = ParameterQueryResult::build_query_result(Some(
+            "roles.unknown=1&groups.name=admin&internalUser.id[eq]=abc&sort_by=asc(roles.name)".to_string()
+        )).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+        assert_eq!(messages, vec![
+            "Unknown filter property 'roles.unknown'",
+            "Unknown filter property 'groups.name'",
+            "Invalid value 'abc' for 'internalUser.id', expected an integer",
+            "Sorting on 'roles.name' is not allowed",
+        ]);
+    }
+
+    #[test]
+    fn given_include_should_load_related_rows_in_one_query() {
+        let relations = Entity::relations();
+        let roles = relations.iter().find(|relation| relation.path == "roles").unwrap();
+        let sql = include_query(roles, vec![Value::from(1), Value::from(2)])
+            .unwrap()
+            .to_string(PostgresQueryBuilder);
+
+        assert_eq!(sql, concat!(
+            r#"SELECT DISTINCT "authorization_role".*, "internal_user"."user_id" AS "__include_key" FROM "internal_user" "#,
+            r#"INNER JOIN "authorization_role_internal_user" ON "internal_user"."id" = "authorization_role_internal_user"."internal_user_id" "#,
+            r#"INNER JOIN "authorization_role" ON "authorization_role_internal_user"."role_id" = "authorization_role"."id" "#,
+            r#"WHERE "internal_user"."user_id" IN (1, 2)"#,
+        ));
+        assert!(include_query(roles, vec![]).is_none());
+    }
+
+    #[test]
+    fn given_included_rows_should_embed_them_under_their_parent() {
+        let mut rows = vec![json!({"id": 1}), json!({"id": 2})];
+        let related = vec![
+            json!({"name": "TOP_LEVEL", "__include_key": 1}),
+            json!({"name": "SUPPORT", "__include_key": 1}),
+        ];
+
+        embed_included(&mut rows, "id", "roles", true, related.clone());
+        assert_eq!(rows[0]["roles"], json!([{"name": "TOP_LEVEL"}, {"name": "SUPPORT"}]));
+        assert_eq!(rows[1]["roles"], json!([]));
+
+        embed_included(&mut rows, "id", "internalUser", false, related);
+        assert_eq!(rows[0]["internalUser"], json!({"name": "TOP_LEVEL"}));
+        assert_eq!(rows[1]["internalUser"], JsonValue::Null);
+    }
+
+    #[test]
+    fn given_invalid_includes_should_return_errors() {
+        let query_result = ParameterQueryResult::build_query_result(Some("include=roles,groups&fields=email".to_string())).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+        assert_eq!(messages, vec!["Including 'roles' requires the 'id' field", "Unknown include 'groups'"]);
+    }
+
+    fn aggregate_sql(query: &str) -> String {
+        let query_result = ParameterQueryResult::build_query_result(Some(query.to_string())).unwrap();
+        let query_result = QueryBuilder::validate::<Entity>(query_result).unwrap();
+
+        QueryBuilder::generate_aggregate(Entity::find(), query_result)
+            .build(DbBackend::Postgres)
+            .to_string()
+    }
+
+    #[test]
+    fn given_truncated_group_should_count_rows_per_day() {
+        let sql = aggregate_sql("group_by=day(createdOn)&aggregate=count(*),max(updatedOn)&createdOn[gte]=2024-01-01");
+
+        assert!(sql.starts_with(concat!(
+            r#"SELECT date_trunc('day', "user_base"."created_on") AS "day(createdOn)", "#,
+            r#"COUNT(*) AS "count(*)", MAX("user_base"."updated_on") AS "max(updatedOn)" FROM "user_base""#,
+        )), "{}", sql);
+        assert!(sql.contains(r#"WHERE "user_base"."created_on" >= '2024-01-01 00:00:00 +00:00'"#), "{}", sql);
+        assert!(sql.contains(r#"GROUP BY date_trunc('day', "user_base"."created_on") ORDER BY date_trunc('day', "user_base"."created_on") ASC"#), "{}", sql);
+        assert!(!sql.contains(r#""user_base"."id" ASC"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_group_on_to_many_relation_should_join_it() {
+        let sql = aggregate_sql("group_by=roles.name");
+
+        assert!(sql.starts_with(r#"SELECT "authorization_role"."name" AS "roles.name", COUNT(*) AS "count(*)" FROM "user_base""#), "{}", sql);
+        assert!(sql.contains(r#"LEFT JOIN "authorization_role" ON "authorization_role_internal_user"."role_id" = "authorization_role"."id""#), "{}", sql);
+        assert!(sql.contains(r#"GROUP BY "authorization_role"."name""#), "{}", sql);
+    }
+
+    #[test]
+    fn given_invalid_groups_and_aggregates_should_return_errors() {
+        let query_result = ParameterQueryResult::build_query_result(Some(
+            "group_by=month(email),password&aggregate=sum(createdOn),max(roles.name)".to_string()
+        )).unwrap();
+        let errors = QueryBuilder::validate::<Entity>(query_result).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+        assert_eq!(messages, vec![
+            "Grouping by month requires a date field, 'email' is not one",
+            "Unknown group property 'password'",
+            "Aggregate 'sum' requires a numeric field, 'createdOn' is not one",
+            "Aggregating 'roles.name' is not allowed",
+        ]);
+    }
+
+    fn cursor_token(sort: &str, values: Vec<JsonValue>, backward: bool) -> String {
+        Cursor {
+            sort: sort.to_string(),
+            values,
+            position: 0,
+            backward,
+        }.encode(cursor_secret())
+    }
+
+    #[test]
+    fn given_sort_without_cursor_column_should_break_ties_on_it() {
+        let sql = validated_sql("sort_by=desc(lastName)");
+
+        assert!(sql.contains(r#"ORDER BY "user_base"."last_name" DESC, "user_base"."id" ASC"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_cursor_should_continue_after_its_row_in_mixed_directions() {
+        let token = cursor_token(
+            "last_name:desc:nulls_first,created_on:asc:nulls_last,id:asc:nulls_last",
+            vec![json!("Smith"), json!("2024-01-02T03:04:05+00:00"), json!(42)],
+            false,
+        );
+        let sql = validated_sql(&format!("sort_by=desc(lastName),asc(createdOn)&cursor={}", token));
+
+        assert!(sql.contains(concat!(
+            r#"WHERE "user_base"."last_name" < 'Smith' OR ("user_base"."last_name" = 'Smith' AND "#,
+            r#"("user_base"."created_on" > '2024-01-02 03:04:05 +00:00' OR "user_base"."created_on" IS NULL)) OR "#,
+            r#"("user_base"."last_name" = 'Smith' AND "user_base"."created_on" = '2024-01-02 03:04:05 +00:00' AND "user_base"."id" > 42) ORDER BY"#,
+        )), "{}", sql);
+        assert!(sql.contains(r#"ORDER BY "user_base"."last_name" DESC, "user_base"."created_on" ASC, "user_base"."id" ASC"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_cursor_on_null_value_should_compare_by_null_placement() {
+        let token = cursor_token("last_name:desc:nulls_first,id:asc:nulls_last", vec![JsonValue::Null, json!(7)], false);
+        let sql = validated_sql(&format!("sort_by=desc(lastName)&cursor={}", token));
+
+        assert!(sql.contains(r#"WHERE "user_base"."last_name" IS NOT NULL OR ("user_base"."last_name" IS NULL AND "user_base"."id" > 7) ORDER BY"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_backward_cursor_should_read_before_its_row_in_reverse() {
+        let token = cursor_token("created_on:desc:nulls_first,id:asc:nulls_last", vec![json!("2024-01-02T03:04:05+00:00"), json!(42)], true);
+        let sql = validated_sql(&format!("sort_by=desc(createdOn)&cursor={}", token));
+
+        assert!(sql.contains(r#""user_base"."created_on" > '2024-01-02 03:04:05 +00:00' OR "user_base"."created_on" IS NULL"#), "{}", sql);
+        assert!(sql.contains(r#""user_base"."id" < 42"#), "{}", sql);
+        assert!(sql.contains(r#"ORDER BY "user_base"."created_on" ASC NULLS LAST, "user_base"."id" DESC NULLS FIRST"#), "{}", sql);
+    }
+
+    #[test]
+    fn given_invalid_cursors_should_return_errors() {
+        let validate = |query: String| {
+            let query_result = ParameterQueryResult::build_query_result(Some(query)).unwrap();
+            QueryBuilder::validate::<Entity>(query_result).unwrap_err()[0].message.clone()
+        };
+        let token = cursor_token("id:asc:nulls_last", vec![json!(42)], false);
+        let (payload, _) = token.split_once('.').unwrap();
+        let mistyped = cursor_token("id:asc:nulls_last", vec![json!("forty-two")], false);
+
+        assert_eq!(validate(format!("sort_by=desc(createdOn)&cursor={}", token)), "Cursor was issued for a different sort_by");
+        assert_eq!(validate(format!("cursor={}.c2lnbmF0dXJl", payload)), "Invalid cursor");
+        assert_eq!(validate(format!("cursor={}", mistyped)), "Invalid cursor");
+    }
+
+    #[test]
+    fn given_cursor_positions_should_count_rows_before_page() {
+        let cursor = |position: u64, backward: bool| Cursor {
+            sort: "id:asc:nulls_last".to_string(),
+            values: vec![json!(position)],
+            position,
+            backward,
+        };
+
+        assert_eq!(preceding_count(None, None, 20, true), 0);
+        assert_eq!(preceding_count(None, Some(40), 20, true), 40);
+        assert_eq!(preceding_count(Some(&cursor(39, false)), None, 20, true), 40);
+        assert_eq!(preceding_count(Some(&cursor(40, true)), None, 20, true), 20);
+        assert_eq!(preceding_count(Some(&cursor(15, true)), None, 20, true), 0);
+        assert_eq!(preceding_count(Some(&cursor(60, true)), None, 12, false), 0);
+    }
+
+    #[test]
+    fn given_last_page_should_end_list_unless_offset_is_past_the_end() {
+        let cursor = Cursor { sort: "id:asc:nulls_last".to_string(), values: vec![json!(1)], position: 1, backward: false };
+
+        assert!(ends_list(None, None, 0, false));
+        assert!(ends_list(None, Some(40), 7, false));
+        assert!(!ends_list(None, Some(40), 20, true));
+        assert!(!ends_list(None, Some(5000), 0, false));
+        assert!(!ends_list(Some(&cursor), None, 7, false));
+    }
 }
@@ -0,0 +1,181 @@
+use std::sync::OnceLock;
+
+use sea_orm::{AccessMode, ConnectionTrait, DatabaseConnection, DatabaseTransaction, EntityTrait, JsonValue, QueryTrait, Select, Statement, TransactionTrait};
+
+use crate::database::database_error::database_error;
+use crate::global::error_handling::ErrorDetails;
+
+/// `statement_timeout` used when `STATEMENT_TIMEOUT_MS` isn't set
+pub const DEFAULT_STATEMENT_TIMEOUT_MS: u64 = 5000;
+
+/// Limits put on the queries of a request so a single expensive filter can't hold on to
+/// connections of the pool
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueryLimits {
+    /// Postgres `statement_timeout` of every statement of a request, `0` disables it
+    pub statement_timeout_ms: u64,
+    /// Highest planner cost a page, count or aggregate query may have, see [`check_cost`]
+    pub max_cost: Option<f64>,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            statement_timeout_ms: DEFAULT_STATEMENT_TIMEOUT_MS,
+            max_cost: None,
+        }
+    }
+}
+
+impl QueryLimits {
+    /// Reads the values of `STATEMENT_TIMEOUT_MS` and `QUERY_MAX_COST`, unset or empty values
+    /// keep the defaults
+    fn parse(statement_timeout: Option<String>, max_cost: Option<String>) -> Result<QueryLimits, String> {
+        let mut limits = QueryLimits::default();
+
+        if let Some(value) = statement_timeout.filter(|value| !value.is_empty()) {
+            limits.statement_timeout_ms = value
+                .parse()
+                .map_err(|_| format!("STATEMENT_TIMEOUT_MS '{}' is not a number of milliseconds", value))?;
+        }
+
+        if let Some(value) = max_cost.filter(|value| !value.is_empty()) {
+            match value.parse::<f64>() {
+                Ok(cost) if cost > 0.0 && cost.is_finite() => limits.max_cost = Some(cost),
+                _ => return Err(format!("QUERY_MAX_COST '{}' is not a positive number", value)),
+            }
+        }
+
+        Ok(limits)
+    }
+}
+
+static LIMITS: OnceLock<QueryLimits> = OnceLock::new();
+
+/// Sets the limits from `STATEMENT_TIMEOUT_MS` and `QUERY_MAX_COST` at startup
+pub fn init_query_limits(statement_timeout: Option<String>, max_cost: Option<String>) -> Result<(), String> {
+    let limits = QueryLimits::parse(statement_timeout, max_cost)?;
+
+    LIMITS.set(limits).map_err(|_| "Query limits are already set".to_string())
+}
+
+/// Limits set by [`init_query_limits`], the defaults when they weren't set
+pub fn query_limits() -> &'static QueryLimits {
+    LIMITS.get_or_init(QueryLimits::default)
+}
+
+/// Read only transaction for the queries of one request, with the `statement_timeout` of
+/// [`query_limits`] applied to it alone
+pub async fn begin_guarded(db: &DatabaseConnection) -> Result<DatabaseTransaction, Vec<ErrorDetails>> {
+    let transaction = db
+        .begin_with_config(None, Some(AccessMode::ReadOnly))
+        .await
+        .map_err(database_error)?;
+
+    let statement = Statement::from_string(
+        transaction.get_database_backend(),
+        format!("SET LOCAL statement_timeout = {}", query_limits().statement_timeout_ms),
+    );
+    transaction.execute(statement).await.map_err(database_error)?;
+
+    Ok(transaction)
+}
+
+/// Top node of the Postgres plan of the select, from `EXPLAIN` without running it
+pub async fn query_plan<C: ConnectionTrait, E: EntityTrait>(db: &C, select: &Select<E>) -> Result<JsonValue, Vec<ErrorDetails>> {
+    let statement = select.build(db.get_database_backend());
+    let explain = Statement::from_sql_and_values(
+        statement.db_backend,
+        format!("EXPLAIN (FORMAT JSON) {}", statement.sql),
+        statement.values.map(|values| values.0).unwrap_or_default(),
+    );
+
+    let plan: Option<JsonValue> = match db.query_one(explain).await.map_err(database_error)? {
+        Some(row) => Some(row.try_get("", "QUERY PLAN").map_err(database_error)?),
+        None => None,
+    };
+
+    Ok(plan
+        .and_then(|mut plan| plan.pointer_mut("/0/Plan").map(JsonValue::take))
+        .unwrap_or_default())
+}
+
+/// Rejects the select before it runs when its estimated cost is over `QUERY_MAX_COST`. The
+/// cost of a `LIMIT` only covers the rows it returns, so the plan under it is compared instead,
+/// as a sort or aggregate below it reads every row anyway.
+pub async fn check_cost<C: ConnectionTrait, E: EntityTrait>(db: &C, select: &Select<E>) -> Result<(), Vec<ErrorDetails>> {
+    let Some(max_cost) = query_limits().max_cost else {
+        return Ok(());
+    };
+
+    let plan = query_plan(db, select).await?;
+    cost_error(&plan, max_cost).map_or(Ok(()), |error| Err(vec![error]))
+}
+
+fn cost_error(plan: &JsonValue, max_cost: f64) -> Option<ErrorDetails> {
+    let plan = match plan.get("Node Type").and_then(JsonValue::as_str) {
+        Some("Limit") => plan.pointer("/Plans/0").unwrap_or(plan),
+        _ => plan,
+    };
+    let cost = plan.get("Total Cost").and_then(JsonValue::as_f64)?;
+    if cost <= max_cost {
+        return None;
+    }
+
+    Some(ErrorDetails::bad_request(format!(
+        "Query is too expensive to run, estimated cost {:.0} is over the limit of {:.0}. Filter on indexed fields or lower the limit",
+        cost, max_cost,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use serde_json::json;
+
+    use crate::database::query_guard::{cost_error, QueryLimits, DEFAULT_STATEMENT_TIMEOUT_MS};
+
+    #[test]
+    fn given_limit_values_should_parse() {
+        let limits = QueryLimits::parse(Some("250".to_string()), Some("10000.5".to_string()));
+        let defaults = QueryLimits::parse(None, Some("".to_string()));
+
+        assert_eq!(limits, Ok(QueryLimits { statement_timeout_ms: 250, max_cost: Some(10000.5) }));
+        assert_eq!(defaults, Ok(QueryLimits { statement_timeout_ms: DEFAULT_STATEMENT_TIMEOUT_MS, max_cost: None }));
+    }
+
+    #[test]
+    fn given_invalid_limit_values_should_return_error() {
+        assert_eq!(
+            QueryLimits::parse(Some("5s".to_string()), None),
+            Err("STATEMENT_TIMEOUT_MS '5s' is not a number of milliseconds".to_string())
+        );
+        assert_eq!(
+            QueryLimits::parse(None, Some("-1".to_string())),
+            Err("QUERY_MAX_COST '-1' is not a positive number".to_string())
+        );
+    }
+
+    #[test]
+    fn given_plan_over_max_cost_should_reject() {
+        let plan = json!({"Node Type": "Seq Scan", "Total Cost": 182344.75, "Plan Rows": 1000});
+        let error = cost_error(&plan, 50000.0).unwrap();
+
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(error.message, "Query is too expensive to run, estimated cost 182345 is over the limit of 50000. Filter on indexed fields or lower the limit");
+        assert!(cost_error(&plan, 200000.0).is_none());
+        assert!(cost_error(&json!({}), 1.0).is_none());
+    }
+
+    #[test]
+    fn given_limited_plan_should_compare_cost_under_limit() {
+        let plan = json!({
+            "Node Type": "Limit",
+            "Total Cost": 12.5,
+            "Plans": [{"Node Type": "Sort", "Total Cost": 182344.75}],
+        });
+
+        assert!(cost_error(&plan, 50000.0).is_some());
+        assert!(cost_error(&json!({"Node Type": "Limit", "Total Cost": 12.5, "Plans": [{"Total Cost": 40.0}]}), 50000.0).is_none());
+    }
+}
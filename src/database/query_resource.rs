@@ -0,0 +1,140 @@
+use change_case::camel_case;
+use sea_orm::{ColumnTrait, ColumnType, EntityTrait, IdenStatic, Iterable, RelationDef, RelationType};
+
+use crate::global::parameter_query_builder::QueryFilter;
+
+pub const DEFAULT_LIMIT: u64 = 200;
+pub const MAX_LIMIT: u64 = 999;
+
+pub const EQUALITY_FILTERS: [QueryFilter; 4] = [QueryFilter::EQ, QueryFilter::NE, QueryFilter::IN, QueryFilter::NIN];
+pub const RANGE_FILTERS: [QueryFilter; 5] = [QueryFilter::GT, QueryFilter::GTE, QueryFilter::LT, QueryFilter::LTE, QueryFilter::BETWEEN];
+pub const TEXT_FILTERS: [QueryFilter; 7] = [
+    QueryFilter::LIKE,
+    QueryFilter::ILIKE,
+    QueryFilter::STARTSWITH,
+    QueryFilter::ISTARTSWITH,
+    QueryFilter::ENDSWITH,
+    QueryFilter::IENDSWITH,
+    QueryFilter::IEQ,
+];
+pub const NULL_FILTERS: [QueryFilter; 2] = [QueryFilter::NULL, QueryFilter::NOTNULL];
+
+/// Query settings of an entity that is listed through the parameter query builder
+pub trait QueryResource: EntityTrait {
+    /// Limit used when the request doesn't give one
+    fn default_limit() -> u64 {
+        DEFAULT_LIMIT
+    }
+
+    /// Largest limit a request may ask for
+    fn max_limit() -> u64 {
+        MAX_LIMIT
+    }
+
+    /// Unique, non null column sorted on last when the request doesn't, so no two rows tie and
+    /// a cursor points at a single row
+    fn cursor_column() -> &'static str {
+        "id"
+    }
+
+    /// Fields that can be filtered, sorted on or picked with `fields=`, any other property is rejected
+    fn query_fields() -> Vec<QueryField>;
+
+    /// Text columns searched by `q=`, search is rejected when there are none. Keep the order in
+    /// line with the resource's full-text index so Postgres can use it.
+    fn search_columns() -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// Related entities whose fields can be filtered or sorted on through `<path>.<field>`,
+    /// and that can be embedded with `include=<path>` when marked includable
+    fn relations() -> Vec<QueryRelation> {
+        vec![]
+    }
+}
+
+/// A column exposed to filters, sorts and sparse fieldsets under its public API name
+#[derive(Debug, Clone)]
+pub struct QueryField {
+    pub column: String,
+    pub public_name: String,
+    pub filters: Vec<QueryFilter>,
+    pub sortable: bool,
+}
+
+impl QueryField {
+    /// Exposes the column under its camelCase name, without any filter and not sortable
+    pub fn new(column: &str) -> Self {
+        QueryField {
+            column: column.to_string(),
+            public_name: camel_case(column),
+            filters: vec![],
+            sortable: false,
+        }
+    }
+
+    pub fn filters(mut self, filters: &[QueryFilter]) -> Self {
+        self.filters.extend_from_slice(filters);
+        self
+    }
+
+    pub fn sortable(mut self) -> Self {
+        self.sortable = true;
+        self
+    }
+}
+
+/// A related entity reached by following relations from the resource, its fields are exposed
+/// under `<path>.<public name>`
+pub struct QueryRelation {
+    pub path: String,
+    /// Relations from the resource to the related entity, in join order
+    pub relations: Vec<RelationDef>,
+    pub fields: Vec<QueryField>,
+    /// Whether `include=<path>` may embed the related rows in every resource row
+    pub includable: bool,
+    table: String,
+    column_types: Vec<(String, ColumnType)>,
+}
+
+impl QueryRelation {
+    pub fn new<R: EntityTrait>(path: &str, relations: Vec<RelationDef>) -> Self {
+        QueryRelation {
+            path: path.to_string(),
+            relations,
+            fields: vec![],
+            includable: false,
+            table: R::default().table_name().to_string(),
+            column_types: R::Column::iter()
+                .map(|column| (column.as_str().to_string(), column.def().get_column_type().clone()))
+                .collect(),
+        }
+    }
+
+    pub fn field(mut self, field: QueryField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn includable(mut self) -> Self {
+        self.includable = true;
+        self
+    }
+
+    /// Whether a resource row can have more than one related row, these relations are filtered
+    /// through `EXISTS` and can't be sorted on
+    pub fn is_to_many(&self) -> bool {
+        self.relations.iter().any(|relation| relation.rel_type == RelationType::HasMany)
+    }
+
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn column_type(&self, column: &str) -> Option<&ColumnType> {
+        self.column_types
+            .iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, column_type)| column_type)
+    }
+}
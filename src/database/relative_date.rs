@@ -0,0 +1,185 @@
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+
+/// Resolves a relative date value against the request clock.
+///
+/// A value is an anchor with an optional signed offset, `now`, `today-7d`,
+/// `start_of_month+P1M`, or an offset from now on its own such as `-P7D`.
+/// Anchors are `now`, `today`, `start_of_week`, `start_of_month` and `start_of_year`,
+/// offsets are either `<amount><unit>` with the units `s`, `m`, `h`, `d`, `w`, `mo`, `y`
+/// or an ISO 8601 duration. Returns `None` for values that aren't relative.
+pub fn resolve_relative_date(input: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    // an unencoded `+` arrives as a space after form decoding
+    let input = input.replace(' ', "+");
+    let anchor_end = input.find(['+', '-']).unwrap_or(input.len());
+    let (anchor, offset) = input.split_at(anchor_end);
+
+    let today = now.date_naive();
+    let base = match anchor {
+        "now" => now,
+        "" if !offset.is_empty() => now,
+        "today" => start_of_day(today)?,
+        "start_of_week" => start_of_day(today.checked_sub_signed(Duration::try_days(today.weekday().num_days_from_monday() as i64)?)?)?,
+        "start_of_month" => start_of_day(today.with_day(1)?)?,
+        "start_of_year" => start_of_day(today.with_ordinal(1)?)?,
+        _ => return None,
+    };
+
+    if offset.is_empty() {
+        return Some(base);
+    }
+
+    let (sign, amount) = offset.split_at(1);
+    let offset = if amount.starts_with('P') {
+        parse_iso_duration(amount)?
+    } else {
+        parse_short_offset(amount)?
+    };
+
+    if sign == "+" {
+        base.checked_add_months(Months::new(offset.months))?
+            .checked_add_signed(offset.duration)
+    } else {
+        base.checked_sub_months(Months::new(offset.months))?
+            .checked_sub_signed(offset.duration)
+    }
+}
+
+/// Months are kept apart from the rest as their length depends on the date they're added to
+#[derive(Debug, PartialEq)]
+struct RelativeOffset {
+    months: u32,
+    duration: Duration,
+}
+
+fn start_of_day(date: NaiveDate) -> Option<DateTime<Utc>> {
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+fn parse_short_offset(input: &str) -> Option<RelativeOffset> {
+    let unit_start = input.find(|character: char| !character.is_ascii_digit())?;
+    let (amount, unit) = input.split_at(unit_start);
+    let amount: i64 = amount.parse().ok()?;
+
+    let (months, duration) = match unit {
+        "s" => (0, Duration::try_seconds(amount)?),
+        "m" => (0, Duration::try_minutes(amount)?),
+        "h" => (0, Duration::try_hours(amount)?),
+        "d" => (0, Duration::try_days(amount)?),
+        "w" => (0, Duration::try_weeks(amount)?),
+        "mo" => (amount, Duration::zero()),
+        "y" => (amount.checked_mul(12)?, Duration::zero()),
+        _ => return None,
+    };
+
+    Some(RelativeOffset {
+        months: u32::try_from(months).ok()?,
+        duration,
+    })
+}
+
+/// Parses the `PnYnMnWnDTnHnMnS` duration format, every component is optional but one is required
+fn parse_iso_duration(input: &str) -> Option<RelativeOffset> {
+    let mut offset = RelativeOffset {
+        months: 0,
+        duration: Duration::zero(),
+    };
+    let mut in_time = false;
+    let mut has_component = false;
+    let mut amount = String::new();
+
+    for character in input.strip_prefix('P')?.chars() {
+        if character.is_ascii_digit() {
+            amount.push(character);
+            continue;
+        }
+
+        if character == 'T' && !in_time && amount.is_empty() {
+            in_time = true;
+            continue;
+        }
+
+        let value: i64 = amount.parse().ok()?;
+        amount.clear();
+        has_component = true;
+
+        match (in_time, character) {
+            (false, 'Y') => offset.months = offset.months.checked_add(u32::try_from(value.checked_mul(12)?).ok()?)?,
+            (false, 'M') => offset.months = offset.months.checked_add(u32::try_from(value).ok()?)?,
+            (false, 'W') => offset.duration = offset.duration.checked_add(&Duration::try_weeks(value)?)?,
+            (false, 'D') => offset.duration = offset.duration.checked_add(&Duration::try_days(value)?)?,
+            (true, 'H') => offset.duration = offset.duration.checked_add(&Duration::try_hours(value)?)?,
+            (true, 'M') => offset.duration = offset.duration.checked_add(&Duration::try_minutes(value)?)?,
+            (true, 'S') => offset.duration = offset.duration.checked_add(&Duration::try_seconds(value)?)?,
+            _ => return None,
+        }
+    }
+
+    if !amount.is_empty() || !has_component {
+        return None;
+    }
+
+    Some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::database::relative_date::resolve_relative_date;
+
+    fn clock() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 14, 15, 9, 26).unwrap()
+    }
+
+    fn resolve(input: &str) -> Option<String> {
+        resolve_relative_date(input, clock()).map(|date_time| date_time.to_rfc3339())
+    }
+
+    #[test]
+    fn given_anchors_should_resolve_against_clock() {
+        assert_eq!(resolve("now"), Some("2024-03-14T15:09:26+00:00".to_string()));
+        assert_eq!(resolve("today"), Some("2024-03-14T00:00:00+00:00".to_string()));
+        assert_eq!(resolve("start_of_week"), Some("2024-03-11T00:00:00+00:00".to_string()));
+        assert_eq!(resolve("start_of_month"), Some("2024-03-01T00:00:00+00:00".to_string()));
+        assert_eq!(resolve("start_of_year"), Some("2024-01-01T00:00:00+00:00".to_string()));
+    }
+
+    #[test]
+    fn given_short_offsets_should_shift_anchor() {
+        assert_eq!(resolve("now-7d"), Some("2024-03-07T15:09:26+00:00".to_string()));
+        assert_eq!(resolve("today+12h"), Some("2024-03-14T12:00:00+00:00".to_string()));
+        assert_eq!(resolve("start_of_month-1mo"), Some("2024-02-01T00:00:00+00:00".to_string()));
+        assert_eq!(resolve("now-1y"), Some("2023-03-14T15:09:26+00:00".to_string()));
+    }
+
+    #[test]
+    fn given_form_decoded_plus_should_add_offset() {
+        assert_eq!(resolve("today 1d"), Some("2024-03-15T00:00:00+00:00".to_string()));
+    }
+
+    #[test]
+    fn given_iso_duration_should_shift_anchor() {
+        assert_eq!(resolve("-P7D"), Some("2024-03-07T15:09:26+00:00".to_string()));
+        assert_eq!(resolve("today-P1M2DT3H"), Some("2024-02-11T21:00:00+00:00".to_string()));
+        assert_eq!(resolve("now+PT30M"), Some("2024-03-14T15:39:26+00:00".to_string()));
+    }
+
+    #[test]
+    fn given_absolute_or_invalid_value_should_not_resolve() {
+        assert_eq!(resolve("2024-01-01"), None);
+        assert_eq!(resolve("P7D"), None);
+        assert_eq!(resolve("now-7x"), None);
+        assert_eq!(resolve("now-P"), None);
+        assert_eq!(resolve("now-P7"), None);
+        assert_eq!(resolve("tomorrow"), None);
+    }
+
+    #[test]
+    fn given_out_of_range_offset_should_not_resolve() {
+        assert_eq!(resolve("now-999999999999999d"), None);
+        assert_eq!(resolve("now+99999999999999999w"), None);
+        assert_eq!(resolve("now-P999999999999999D"), None);
+        assert_eq!(resolve("now+P100000000000000WT99999999999999H"), None);
+        assert_eq!(resolve("now+200000000d"), None);
+    }
+}
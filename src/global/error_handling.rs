@@ -8,12 +8,23 @@ pub struct ErrorDetailsDto {
     pub message: String,
 }
 
+#[derive(Debug)]
 pub struct ErrorDetails {
     pub status_code: StatusCode,
     pub message: String,
+    /// Seconds to wait before retrying, sent as the `Retry-After` header
+    pub retry_after: Option<u64>,
 }
 
 impl ErrorDetails {
+    pub fn bad_request(message: String) -> Self {
+        ErrorDetails {
+            status_code: StatusCode::BAD_REQUEST,
+            message,
+            retry_after: None,
+        }
+    }
+
     pub fn to_dto(&self) -> ErrorDetailsDto {
         ErrorDetailsDto {
             status_code: self.status_code.as_u16(),
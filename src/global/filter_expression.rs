@@ -0,0 +1,435 @@
+use std::fmt;
+
+use crate::global::error_handling::ErrorDetails;
+use crate::global::parameter_query_builder::{parse_column_filter, ColumnFilter, QueryFilter, QueryOperator};
+
+/// Deepest nesting of parentheses and `not` in a filter expression, the parser recurses once
+/// per level so this bounds its stack use
+pub const MAX_FILTER_DEPTH: usize = 32;
+
+/// Most predicates a filter expression may hold
+pub const MAX_FILTER_PREDICATES: usize = 100;
+
+/// Boolean expression given through the `filter=` parameter, e.g.
+/// `filter=(first_name[eq]=John or last_name[ilike]=smith) and not phone[null]`
+///
+/// Predicates use the same `property[filter]=value` syntax as regular filters, values
+/// containing whitespace or parentheses can be wrapped in single or double quotes.
+/// `not` binds tighter than `and`, which binds tighter than `or`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FilterExpression {
+    And(Vec<FilterExpression>),
+    Or(Vec<FilterExpression>),
+    Not(Box<FilterExpression>),
+    Filter(ColumnFilter),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    OpenParenthesis,
+    CloseParenthesis,
+    And,
+    Or,
+    Not,
+    Predicate(String, String),
+}
+
+impl FilterExpression {
+    pub fn parse(expression: &str) -> Result<FilterExpression, Vec<ErrorDetails>> {
+        let tokens = tokenize(expression)?;
+        let predicate_count = tokens.iter().filter(|token| matches!(token, Token::Predicate(..))).count();
+        if predicate_count > MAX_FILTER_PREDICATES {
+            return Err(vec![ErrorDetails::bad_request(format!(
+                "Filter expression has {} predicates, at most {} are allowed",
+                predicate_count, MAX_FILTER_PREDICATES
+            ))]);
+        }
+
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+
+        let result = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(vec![ErrorDetails::bad_request(format!(
+                "Unexpected {} in filter expression",
+                describe(token)
+            ))]);
+        }
+
+        Ok(result)
+    }
+}
+
+/// Writes the expression back in the syntax [`FilterExpression::parse`] reads, parenthesized
+/// where needed to keep the same tree and with values quoted when they would not read back as is
+impl fmt::Display for FilterExpression {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FilterExpression::Or(expressions) => {
+                write_joined(formatter, expressions, " or ", |expression| matches!(expression, FilterExpression::Or(_)))
+            }
+            FilterExpression::And(expressions) => {
+                write_joined(formatter, expressions, " and ", |expression| {
+                    matches!(expression, FilterExpression::Or(_) | FilterExpression::And(_))
+                })
+            }
+            FilterExpression::Not(expression) => match expression.as_ref() {
+                FilterExpression::Or(_) | FilterExpression::And(_) => write!(formatter, "not ({})", expression),
+                expression => write!(formatter, "not {}", expression),
+            },
+            FilterExpression::Filter(filter) => {
+                let operator = match filter.operator {
+                    QueryOperator::AND => "",
+                    QueryOperator::OR => "[or]",
+                };
+                write!(formatter, "{}{}[{}]", filter.property, operator, filter.filter.as_str())?;
+
+                match filter.filter {
+                    QueryFilter::NULL | QueryFilter::NOTNULL => Ok(()),
+                    _ => write!(formatter, "={}", quote_value(&filter.value)),
+                }
+            }
+        }
+    }
+}
+
+fn write_joined(
+    formatter: &mut fmt::Formatter,
+    expressions: &[FilterExpression],
+    separator: &str,
+    needs_parentheses: impl Fn(&FilterExpression) -> bool,
+) -> fmt::Result {
+    for (index, expression) in expressions.iter().enumerate() {
+        if index > 0 {
+            formatter.write_str(separator)?;
+        }
+
+        if needs_parentheses(expression) {
+            write!(formatter, "({})", expression)?;
+        } else {
+            write!(formatter, "{}", expression)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn quote_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.starts_with(['\'', '"'])
+        || value.chars().any(|c| c.is_whitespace() || c == '(' || c == ')');
+    if !needs_quotes {
+        return value.to_string();
+    }
+
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, Vec<ErrorDetails>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&current) = chars.peek() {
+        match current {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::OpenParenthesis);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::CloseParenthesis);
+            }
+            _ => {
+                let mut key = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '=' {
+                        break;
+                    }
+                    key.push(c);
+                    chars.next();
+                }
+
+                if chars.peek() != Some(&'=') {
+                    tokens.push(match key.to_lowercase().as_str() {
+                        "and" => Token::And,
+                        "or" => Token::Or,
+                        "not" => Token::Not,
+                        _ => Token::Predicate(key, String::new()),
+                    });
+                    continue;
+                }
+
+                // Skip `=`
+                chars.next();
+
+                let mut value = String::new();
+                match chars.peek() {
+                    Some(&quote) if quote == '\'' || quote == '"' => {
+                        chars.next();
+
+                        let mut is_closed = false;
+                        while let Some(c) = chars.next() {
+                            match c {
+                                '\\' => {
+                                    if let Some(escaped) = chars.next() {
+                                        value.push(escaped);
+                                    }
+                                }
+                                c if c == quote => {
+                                    is_closed = true;
+                                    break;
+                                }
+                                c => value.push(c),
+                            }
+                        }
+
+                        if !is_closed {
+                            return Err(vec![ErrorDetails::bad_request(format!(
+                                "Unterminated quoted value for '{}' in filter expression",
+                                key
+                            ))]);
+                        }
+                    }
+                    _ => {
+                        while let Some(&c) = chars.peek() {
+                            if c.is_whitespace() || c == ')' {
+                                break;
+                            }
+                            value.push(c);
+                            chars.next();
+                        }
+                    }
+                }
+
+                tokens.push(Token::Predicate(key, value));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::OpenParenthesis => "'('".to_string(),
+        Token::CloseParenthesis => "')'".to_string(),
+        Token::And => "'and'".to_string(),
+        Token::Or => "'or'".to_string(),
+        Token::Not => "'not'".to_string(),
+        Token::Predicate(key, _) => format!("'{}'", key),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Parentheses and `not` entered but not yet left
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<FilterExpression, Vec<ErrorDetails>> {
+        let mut expressions = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expressions.push(self.parse_and()?);
+        }
+
+        if expressions.len() == 1 {
+            return Ok(expressions.remove(0));
+        }
+
+        Ok(FilterExpression::Or(expressions))
+    }
+
+    fn parse_and(&mut self) -> Result<FilterExpression, Vec<ErrorDetails>> {
+        let mut expressions = vec![self.parse_unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expressions.push(self.parse_unary()?);
+        }
+
+        if expressions.len() == 1 {
+            return Ok(expressions.remove(0));
+        }
+
+        Ok(FilterExpression::And(expressions))
+    }
+
+    fn enter(&mut self) -> Result<(), Vec<ErrorDetails>> {
+        self.depth += 1;
+        if self.depth > MAX_FILTER_DEPTH {
+            return Err(vec![ErrorDetails::bad_request(format!(
+                "Filter expression nests too deeply, at most {} levels are allowed",
+                MAX_FILTER_DEPTH
+            ))]);
+        }
+
+        Ok(())
+    }
+
+    fn parse_unary(&mut self) -> Result<FilterExpression, Vec<ErrorDetails>> {
+        match self.next() {
+            Some(Token::Not) => {
+                self.enter()?;
+                let expression = self.parse_unary()?;
+                self.depth -= 1;
+
+                Ok(FilterExpression::Not(Box::new(expression)))
+            }
+            Some(Token::OpenParenthesis) => {
+                self.enter()?;
+                let expression = self.parse_or()?;
+                self.depth -= 1;
+
+                match self.next() {
+                    Some(Token::CloseParenthesis) => Ok(expression),
+                    _ => Err(vec![ErrorDetails::bad_request(
+                        "Missing closing parenthesis in filter expression".to_string()
+                    )]),
+                }
+            }
+            Some(Token::Predicate(key, value)) => {
+                match parse_column_filter(&key, &value)? {
+                    Some(column_filter) => Ok(FilterExpression::Filter(column_filter)),
+                    None => Err(vec![ErrorDetails::bad_request(format!(
+                        "Filter '{}' is missing a value in filter expression",
+                        key
+                    ))]),
+                }
+            }
+            Some(token) => Err(vec![ErrorDetails::bad_request(format!(
+                "Unexpected {} in filter expression",
+                describe(&token)
+            ))]),
+            None => Err(vec![ErrorDetails::bad_request(
+                "Filter expression ended unexpectedly".to_string()
+            )]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::global::filter_expression::{FilterExpression, MAX_FILTER_DEPTH, MAX_FILTER_PREDICATES};
+    use crate::global::parameter_query_builder::{ColumnFilter, QueryFilter, QueryOperator};
+
+    fn filter(filter: QueryFilter, property: &str, value: &str) -> FilterExpression {
+        FilterExpression::Filter(ColumnFilter {
+            operator: QueryOperator::AND,
+            filter,
+            property: property.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    #[test]
+    fn given_single_predicate_should_return_filter() {
+        let result = FilterExpression::parse("first_name[eq]=John").unwrap();
+
+        assert_eq!(result, filter(QueryFilter::EQ, "first_name", "John"));
+    }
+
+    #[test]
+    fn given_grouped_expressions_should_return_nested_tree() {
+        let result = FilterExpression::parse("(a=1 or b[gt]=2) and (c[null] OR d[in]=x,y)").unwrap();
+        let expected = FilterExpression::And(vec![
+            FilterExpression::Or(vec![
+                filter(QueryFilter::EQ, "a", "1"),
+                filter(QueryFilter::GT, "b", "2"),
+            ]),
+            FilterExpression::Or(vec![
+                filter(QueryFilter::NULL, "c", ""),
+                filter(QueryFilter::IN, "d", "x,y"),
+            ]),
+        ]);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn given_and_without_parentheses_should_bind_tighter_than_or() {
+        let result = FilterExpression::parse("a=1 or b=2 and not c=3").unwrap();
+        let expected = FilterExpression::Or(vec![
+            filter(QueryFilter::EQ, "a", "1"),
+            FilterExpression::And(vec![
+                filter(QueryFilter::EQ, "b", "2"),
+                FilterExpression::Not(Box::new(filter(QueryFilter::EQ, "c", "3"))),
+            ]),
+        ]);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn given_quoted_value_should_keep_whitespace_and_parentheses() {
+        let result = FilterExpression::parse("first_name[eq]='Mary (Ann) O\\'Neil'").unwrap();
+
+        assert_eq!(result, filter(QueryFilter::EQ, "first_name", "Mary (Ann) O'Neil"));
+    }
+
+    #[test]
+    fn given_unbalanced_parentheses_should_return_error() {
+        assert!(FilterExpression::parse("(a=1 or b=2").is_err());
+        assert!(FilterExpression::parse("a=1 or b=2)").is_err());
+    }
+
+    #[test]
+    fn given_dangling_operator_should_return_error() {
+        assert!(FilterExpression::parse("a=1 and").is_err());
+        assert!(FilterExpression::parse("or a=1").is_err());
+    }
+
+    #[test]
+    fn given_unknown_filter_in_expression_should_return_error() {
+        assert!(FilterExpression::parse("a[unknown]=1").is_err());
+    }
+
+    #[test]
+    fn given_expression_should_display_in_parseable_form() {
+        let expression = FilterExpression::parse("a=1 or (b[gt]=2 or not (c[null] and d[in]=x,y)) and e[eq]=\"two words\"").unwrap();
+
+        assert_eq!(expression.to_string(), r#"a[eq]=1 or (b[gt]=2 or not (c[null] and d[in]=x,y)) and e[eq]="two words""#);
+        assert_eq!(FilterExpression::parse(&expression.to_string()).unwrap(), expression);
+    }
+
+    #[test]
+    fn given_deeply_nested_expression_should_return_error() {
+        let nested = format!("{}a=1{}", "(".repeat(20_000), ")".repeat(20_000));
+        let negated = format!("{}a=1", "not ".repeat(20_000));
+        let allowed = format!("{}a=1{}", "(".repeat(MAX_FILTER_DEPTH), ")".repeat(MAX_FILTER_DEPTH));
+
+        for expression in [nested, negated] {
+            let errors = FilterExpression::parse(&expression).unwrap_err();
+
+            assert_eq!(errors[0].message, "Filter expression nests too deeply, at most 32 levels are allowed");
+        }
+        assert!(FilterExpression::parse(&allowed).is_ok());
+    }
+
+    #[test]
+    fn given_too_many_predicates_should_return_error() {
+        let predicates = vec!["a=1"; MAX_FILTER_PREDICATES + 1].join(" or ");
+        let errors = FilterExpression::parse(&predicates).unwrap_err();
+
+        assert_eq!(errors[0].message, "Filter expression has 101 predicates, at most 100 are allowed");
+        assert!(FilterExpression::parse(&vec!["a=1"; MAX_FILTER_PREDICATES].join(" or ")).is_ok());
+    }
+}
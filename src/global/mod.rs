@@ -1,3 +1,6 @@
 pub mod parameter_query_builder;
+pub mod parameter_query_document;
+pub mod parameter_query_string;
+pub mod filter_expression;
 pub mod error_handling;
 pub mod response_builder;
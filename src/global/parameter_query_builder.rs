@@ -1,14 +1,30 @@
-use std::collections::HashMap;
-use std::convert::Infallible;
 use std::str::FromStr;
 
 use async_trait::async_trait;
 use axum::extract::FromRequestParts;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use http::request::Parts;
 
+use crate::database::cursor::Cursor;
+use crate::global::error_handling::ErrorDetails;
+use crate::global::filter_expression::FilterExpression;
+use crate::global::response_builder::{DataListResponse, MetaListData};
+
 #[derive(Debug)]
 pub struct ParameterQueryBuilder(pub ParameterQueryResult);
 
+/// Parameters that configure the query itself, every other parameter is a filter
+pub(crate) const RESERVED_PARAMETERS: [&str; 12] = [
+    "limit", "page", "offset", "cursor", "count", "sort_by", "filter", "fields", "q", "include", "group_by", "aggregate",
+];
+
+/// Highest number of rows `offset` or `page` may skip, Postgres takes `OFFSET` as a bigint
+pub const MAX_OFFSET: u64 = i64::MAX as u64;
+
+/// Sort property ordering by full-text search rank, only available with `q=`
+pub const RELEVANCE: &str = "relevance";
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum QuerySort {
     ASC,
@@ -27,6 +43,145 @@ impl FromStr for QuerySort {
     }
 }
 
+impl QuerySort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuerySort::ASC => "asc",
+            QuerySort::DESC => "desc",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum QueryNulls {
+    FIRST,
+    LAST,
+}
+
+impl FromStr for QueryNulls {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "FIRST" => Ok(QueryNulls::FIRST),
+            "LAST" => Ok(QueryNulls::LAST),
+            _ => Err(()),
+        }
+    }
+}
+
+impl QueryNulls {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryNulls::FIRST => "nulls_first",
+            QueryNulls::LAST => "nulls_last",
+        }
+    }
+}
+
+/// How the total of a list is counted, `count=estimate` reads the planner statistics instead
+/// of counting every row and `count=none` leaves the total out
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum QueryCount {
+    EXACT,
+    ESTIMATE,
+    NONE,
+}
+
+impl FromStr for QueryCount {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "EXACT" => Ok(QueryCount::EXACT),
+            "ESTIMATE" => Ok(QueryCount::ESTIMATE),
+            "NONE" => Ok(QueryCount::NONE),
+            _ => Err(()),
+        }
+    }
+}
+
+impl QueryCount {
+    /// Name of the count mode as written in a query string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryCount::EXACT => "exact",
+            QueryCount::ESTIMATE => "estimate",
+            QueryCount::NONE => "none",
+        }
+    }
+}
+
+/// Date truncation applied to a `group_by` property, e.g. `group_by=day(created_on)`
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum QueryTruncate {
+    DAY,
+    WEEK,
+    MONTH,
+    YEAR,
+}
+
+impl FromStr for QueryTruncate {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "DAY" => Ok(QueryTruncate::DAY),
+            "WEEK" => Ok(QueryTruncate::WEEK),
+            "MONTH" => Ok(QueryTruncate::MONTH),
+            "YEAR" => Ok(QueryTruncate::YEAR),
+            _ => Err(()),
+        }
+    }
+}
+
+impl QueryTruncate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryTruncate::DAY => "day",
+            QueryTruncate::WEEK => "week",
+            QueryTruncate::MONTH => "month",
+            QueryTruncate::YEAR => "year",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum QueryAggregate {
+    COUNT,
+    SUM,
+    AVG,
+    MIN,
+    MAX,
+}
+
+impl FromStr for QueryAggregate {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "COUNT" => Ok(QueryAggregate::COUNT),
+            "SUM" => Ok(QueryAggregate::SUM),
+            "AVG" => Ok(QueryAggregate::AVG),
+            "MIN" => Ok(QueryAggregate::MIN),
+            "MAX" => Ok(QueryAggregate::MAX),
+            _ => Err(()),
+        }
+    }
+}
+
+impl QueryAggregate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryAggregate::COUNT => "count",
+            QueryAggregate::SUM => "sum",
+            QueryAggregate::AVG => "avg",
+            QueryAggregate::MIN => "min",
+            QueryAggregate::MAX => "max",
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum QueryFilter {
     GT,
@@ -36,6 +191,17 @@ pub enum QueryFilter {
     EQ,
     NE,
     LIKE,
+    ILIKE,
+    STARTSWITH,
+    ISTARTSWITH,
+    ENDSWITH,
+    IENDSWITH,
+    IEQ,
+    IN,
+    NIN,
+    BETWEEN,
+    NULL,
+    NOTNULL,
     CURSOR,
 }
 
@@ -51,12 +217,50 @@ impl FromStr for QueryFilter {
             "EQ" => Ok(QueryFilter::EQ),
             "NE" => Ok(QueryFilter::NE),
             "LIKE" => Ok(QueryFilter::LIKE),
+            "ILIKE" => Ok(QueryFilter::ILIKE),
+            "STARTS_WITH" => Ok(QueryFilter::STARTSWITH),
+            "ISTARTS_WITH" => Ok(QueryFilter::ISTARTSWITH),
+            "ENDS_WITH" => Ok(QueryFilter::ENDSWITH),
+            "IENDS_WITH" => Ok(QueryFilter::IENDSWITH),
+            "IEQ" => Ok(QueryFilter::IEQ),
+            "IN" => Ok(QueryFilter::IN),
+            "NIN" => Ok(QueryFilter::NIN),
+            "BETWEEN" => Ok(QueryFilter::BETWEEN),
+            "NULL" => Ok(QueryFilter::NULL),
+            "NOTNULL" => Ok(QueryFilter::NOTNULL),
             "CURSOR" => Ok(QueryFilter::CURSOR),
             _ => Err(()),
         }
     }
 }
 
+impl QueryFilter {
+    /// Name of the filter as written in a query string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryFilter::GT => "gt",
+            QueryFilter::GTE => "gte",
+            QueryFilter::LT => "lt",
+            QueryFilter::LTE => "lte",
+            QueryFilter::EQ => "eq",
+            QueryFilter::NE => "ne",
+            QueryFilter::LIKE => "like",
+            QueryFilter::ILIKE => "ilike",
+            QueryFilter::STARTSWITH => "starts_with",
+            QueryFilter::ISTARTSWITH => "istarts_with",
+            QueryFilter::ENDSWITH => "ends_with",
+            QueryFilter::IENDSWITH => "iends_with",
+            QueryFilter::IEQ => "ieq",
+            QueryFilter::IN => "in",
+            QueryFilter::NIN => "nin",
+            QueryFilter::BETWEEN => "between",
+            QueryFilter::NULL => "null",
+            QueryFilter::NOTNULL => "notnull",
+            QueryFilter::CURSOR => "cursor",
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum QueryOperator {
     AND,
@@ -78,8 +282,31 @@ impl FromStr for QueryOperator {
 #[derive(Debug, Clone)]
 pub struct ParameterQueryResult {
     pub filter_list: Vec<ColumnFilterList>,
-    pub sort_list: HashMap<QuerySort, Vec<String>>,
-    pub limit: u64,
+    pub filter_expression: Option<FilterExpression>,
+    pub sort_list: Vec<ColumnSort>,
+    pub fields: Vec<String>,
+    /// Related resources embedded in every row, by relation path
+    pub include: Vec<String>,
+    pub group_list: Vec<ColumnGroup>,
+    pub aggregate_list: Vec<ColumnAggregate>,
+    pub limit: Option<u64>,
+    pub page: Option<u64>,
+    pub offset: Option<u64>,
+    /// Token given with `cursor=`, decoded into `keyset` on validation
+    pub cursor: Option<String>,
+    /// Row the page starts after, or ends before when going backward
+    pub keyset: Option<Cursor>,
+    pub count: QueryCount,
+    pub search: Option<TextSearch>,
+    /// Clock relative date values such as `now-7d` are resolved against, taken once per request
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Full-text search given through `q=`, the searched columns are set by the resource on validation
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextSearch {
+    pub query: String,
+    pub columns: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -90,260 +317,530 @@ pub struct ColumnFilter {
     pub value: String,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnSort {
+    pub property: String,
+    pub sort: QuerySort,
+    pub nulls: Option<QueryNulls>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ColumnFilterList {
     pub operator: QueryOperator,
     pub filter_list: Vec<ColumnFilter>,
 }
 
-impl ParameterQueryResult {
-    fn build_query_result(query: Option<String>) -> ParameterQueryResult {
-        // TODO: Figure out how to handle errors properly for the application in general
-        let mut result = ParameterQueryResult {
-            filter_list: vec![],
-            sort_list: HashMap::new(),
-            limit: 200,
-        };
+/// Property of a `group_by`, the alias is the group as written and names it in the result rows
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnGroup {
+    pub property: String,
+    pub truncate: Option<QueryTruncate>,
+    pub alias: String,
+}
+
+/// Function of an `aggregate`, without a property for `count(*)`. The alias is the aggregate as
+/// written and names it in the result rows.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnAggregate {
+    pub function: QueryAggregate,
+    pub property: Option<String>,
+    pub alias: String,
+}
 
+impl ParameterQueryResult {
+    pub fn build_query_result(query: Option<String>) -> Result<ParameterQueryResult, Vec<ErrorDetails>> {
         let query_string;
         let has_query_string = query.as_ref();
         match has_query_string {
             None => {
-                return result;
+                return Ok(ParameterQueryResult::empty());
             }
             Some(value) => {
                 query_string = value;
             }
         }
 
-        let possible_params = query_string.split("&");
+        // Decodes percent-encoding and `+`, splits each pair on the first `=` and keeps repeated keys in order
+        let possible_params: Vec<(String, String)> = form_urlencoded::parse(query_string.as_bytes())
+            .into_owned()
+            .collect();
+
+        ParameterQueryResult::from_parameters(possible_params)
+    }
+
+    /// Builds the query from decoded key/value pairs, shared by the query string and the JSON search body
+    pub fn from_parameters(possible_params: Vec<(String, String)>) -> Result<ParameterQueryResult, Vec<ErrorDetails>> {
+        let mut result = ParameterQueryResult::empty();
+        let mut errors: Vec<ErrorDetails> = vec![];
 
-        // TODO: Move limit logic to function (create a builder like pattern for ParameterQueryResult?)
         let limit: Vec<_> = possible_params
-            .clone()
-            .filter(|param| {
-                let is_limit_valid: Vec<_> = param.split("limit=").collect();
-                param.contains("limit=") && param.split("limit=").count() == 2 && is_limit_valid[is_limit_valid.len() - 1].len() <= 3
-            })
+            .iter()
+            .filter(|(key, _)| key == "limit")
             .collect();
-        if limit.len() > 0 {
-            result.limit = limit[0].split("limit=").collect::<Vec<_>>()[1].parse().unwrap()
+        if let Some((_, limit)) = limit.first() {
+            match parse_limit(limit) {
+                Ok(value) => result.limit = Some(value),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        if let Some((_, page)) = possible_params.iter().find(|(key, _)| key == "page") {
+            match page.parse::<u64>() {
+                Ok(value) if value > 0 => result.page = Some(value),
+                _ => errors.push(ErrorDetails::bad_request(format!("Invalid page '{}', expected a number greater than 0", page))),
+            }
+        }
+
+        if let Some((_, offset)) = possible_params.iter().find(|(key, _)| key == "offset") {
+            match offset.parse::<u64>() {
+                Ok(value) if value <= MAX_OFFSET => result.offset = Some(value),
+                _ => errors.push(ErrorDetails::bad_request(format!("Invalid offset '{}', expected a number up to {}", offset, MAX_OFFSET))),
+            }
+        }
+
+        if let Some((_, cursor)) = possible_params.iter().find(|(key, _)| key == "cursor") {
+            if !cursor.is_empty() {
+                result.cursor = Some(cursor.clone());
+            }
+        }
+
+        if let Some((_, count)) = possible_params.iter().find(|(key, _)| key == "count") {
+            match QueryCount::from_str(count) {
+                Ok(value) => result.count = value,
+                Err(_) => errors.push(ErrorDetails::bad_request(format!("Invalid count '{}', expected exact, estimate or none", count))),
+            }
+        }
+
+        if let Some((_, query)) = possible_params.iter().find(|(key, _)| key == "q") {
+            if !query.trim().is_empty() {
+                result.search = Some(TextSearch {
+                    query: query.trim().to_string(),
+                    columns: vec![],
+                });
+            }
+        }
+
+        if result.page.is_some() && result.offset.is_some() {
+            errors.push(ErrorDetails::bad_request("Use either page or offset, not both".to_string()));
         }
 
-        // TODO: Move sort logic to function (create a builder like pattern for ParameterQueryResult?)
         let sort_by: Vec<_> = possible_params
-            .clone()
-            .filter(|param| {
-                param.contains("sort_by=") && param.split("sort_by=").count() == 2
-            })
+            .iter()
+            .filter(|(key, _)| key == "sort_by")
             .collect();
 
         if sort_by.is_empty() {
-            let sort_fields = vec!["id".to_string()];
-            let mut mapping = HashMap::new();
-            mapping.insert(QuerySort::ASC, sort_fields);
-
-            result.sort_list = mapping
+            result.sort_list = vec![ColumnSort {
+                property: "id".to_string(),
+                sort: QuerySort::ASC,
+                nulls: None,
+            }];
         } else {
-            let sorts = sort_by
-                .first()
-                .expect("Error finding first sort_by.")
-                .split("sort_by=")
-                .last()
-                .unwrap();
-
-            let sorts_seperated: Vec<_> = sorts
-                .split("),")
-                .map(|s| {
-                    if s.ends_with(")") {
-                        s.to_string()
-                    } else {
-                        format!("{})", s)
-                    }
-                })
-                .collect();
-
-            let mut mapping = HashMap::new();
-            for sort in &sorts_seperated {
-                let query_sort = sort.split("(").min().unwrap();
-
-                let mut query_sort_value = QuerySort::ASC;
-                match QuerySort::from_str(query_sort) {
-                    Ok(parsed_sort) => match parsed_sort {
-                        QuerySort::ASC => {
-                            // Do nothing, use default
-                        }
-                        QuerySort::DESC => {
-                            query_sort_value = QuerySort::DESC
-                        }
-                    },
-                    Err(_) => {
-                        // TODO: handle errors properly
-                        println!("Query sort is neither ASC nor DESC");
-                    }
+            for (_, sorts) in sort_by {
+                match parse_sort_by(sorts) {
+                    Ok(mut sort_list) => result.sort_list.append(&mut sort_list),
+                    Err(mut sort_errors) => errors.append(&mut sort_errors),
                 }
+            }
+        }
+
+        for (_, fields) in possible_params.iter().filter(|(key, _)| key == "fields") {
+            if fields.split(",").any(|field| field.is_empty()) {
+                errors.push(ErrorDetails::bad_request(format!("Fields '{}' contains an empty property name", fields)));
+                continue;
+            }
+
+            result.fields.extend(fields.split(",").map(String::from));
+        }
+
+        for (_, groups) in possible_params.iter().filter(|(key, _)| key == "group_by") {
+            match parse_group_by(groups) {
+                Ok(mut group_list) => result.group_list.append(&mut group_list),
+                Err(mut group_errors) => errors.append(&mut group_errors),
+            }
+        }
 
-                let mut sort_list = sort
-                    .split("(")
-                    .last()
-                    .unwrap()
-                    .to_string();
-                sort_list.pop();
+        for (_, aggregates) in possible_params.iter().filter(|(key, _)| key == "aggregate") {
+            match parse_aggregate(aggregates) {
+                Ok(mut aggregate_list) => result.aggregate_list.append(&mut aggregate_list),
+                Err(mut aggregate_errors) => errors.append(&mut aggregate_errors),
+            }
+        }
+
+        for (_, include) in possible_params.iter().filter(|(key, _)| key == "include") {
+            if include.split(",").any(|path| path.is_empty()) {
+                errors.push(ErrorDetails::bad_request(format!("Include '{}' contains an empty relation name", include)));
+                continue;
+            }
 
-                mapping.insert(query_sort_value, sort_list.split(",").map(String::from).collect());
+            for path in include.split(",") {
+                if !result.include.iter().any(|included| included == path) {
+                    result.include.push(path.to_string());
+                }
             }
+        }
 
-            result.sort_list = mapping
+        let mut filter_expressions: Vec<FilterExpression> = vec![];
+        for (_, expression) in possible_params.iter().filter(|(key, _)| key == "filter") {
+            match FilterExpression::parse(expression) {
+                Ok(filter_expression) => filter_expressions.push(filter_expression),
+                Err(mut expression_errors) => errors.append(&mut expression_errors),
+            }
         }
 
-        // TODO: Move filter logic to function (create a builder like pattern for ParameterQueryResult?)
+        result.filter_expression = match filter_expressions.len() {
+            0 => None,
+            1 => filter_expressions.pop(),
+            _ => Some(FilterExpression::And(filter_expressions)),
+        };
+
         let filters: Vec<_> = possible_params
-            .clone()
-            .filter(|param| {
-                !param.contains("sort_by=") && !param.contains("limit=")
-            })
+            .iter()
+            .filter(|(key, _)| !RESERVED_PARAMETERS.contains(&key.as_str()))
             .collect();
 
-        for filter in &filters {
-            let mut column_filter_list_operator = QueryOperator::AND;
-            let mut cleaned_filter;
-            let check_filter_list_operator = filter.clone();
+        for (key, value) in filters {
+            match parse_filter(key, value) {
+                Ok(Some(column_filter_list)) => result.filter_list.push(column_filter_list),
+                Ok(None) => {}
+                Err(mut filter_errors) => errors.append(&mut filter_errors),
+            }
+        }
 
-            match check_filter_list_operator {
-                s if s.starts_with(&format!("[{:?}]", QueryOperator::OR).to_lowercase()) => {
-                    column_filter_list_operator = QueryOperator::OR;
+        let has_cursor = result.filter_list
+            .iter()
+            .flat_map(|column_filter_list| &column_filter_list.filter_list)
+            .any(|column_filter| column_filter.filter == QueryFilter::CURSOR)
+            || result.cursor.is_some();
+        if has_cursor && (result.page.is_some() || result.offset.is_some()) {
+            errors.push(ErrorDetails::bad_request("Page and offset pagination can't be combined with a cursor".to_string()));
+        }
 
-                    cleaned_filter = check_filter_list_operator.trim_start_matches(&format!("[{:?}]", QueryOperator::OR).to_lowercase()).to_string();
-                }
-                s if s.starts_with(&format!("[{:?}]", QueryOperator::AND).to_lowercase()) => {
-                    cleaned_filter = check_filter_list_operator.trim_start_matches(&format!("[{:?}]", QueryOperator::AND).to_lowercase()).to_string();
-                }
-                s if s.starts_with(&format!("[{:?}]", QueryOperator::OR)) => {
-                    column_filter_list_operator = QueryOperator::OR;
+        if !errors.is_empty() {
+            return Err(errors);
+        }
 
-                    cleaned_filter = check_filter_list_operator.trim_start_matches(&format!("[{:?}]", QueryOperator::OR)).to_string();
-                }
-                s if s.starts_with(&format!("[{:?}]", QueryOperator::AND)) => {
-                    cleaned_filter = check_filter_list_operator.trim_start_matches(&format!("[{:?}]", QueryOperator::AND)).to_string();
-                }
-                _ => {
-                    cleaned_filter = check_filter_list_operator.to_string();
-                }
+        Ok(result)
+    }
+
+    pub(crate) fn empty() -> Self {
+        ParameterQueryResult {
+            filter_list: vec![],
+            filter_expression: None,
+            sort_list: vec![],
+            fields: vec![],
+            include: vec![],
+            group_list: vec![],
+            aggregate_list: vec![],
+            limit: None,
+            page: None,
+            offset: None,
+            cursor: None,
+            keyset: None,
+            count: QueryCount::EXACT,
+            search: None,
+            requested_at: Utc::now(),
+        }
+    }
+
+    /// Number of rows to skip, from either `offset` or `page` using the given limit. `None` for
+    /// a page that would skip more than `MAX_OFFSET` rows, which validation rejects.
+    pub fn skip(&self, limit: u64) -> Option<u64> {
+        self.offset.or_else(|| {
+            self.page
+                .and_then(|page| (page - 1).checked_mul(limit))
+                .filter(|skip| *skip <= MAX_OFFSET)
+        })
+    }
+}
+
+/// Splits `name(argument)` into its name and argument
+fn split_call(call: &str) -> Option<(&str, &str)> {
+    let (name, argument) = call.strip_suffix(")")?.split_once("(")?;
+    if name.is_empty() || argument.is_empty() {
+        return None;
+    }
+
+    Some((name, argument))
+}
+
+/// Parses `group_by=day(created_on),last_name`, date properties can be truncated to a day, week, month or year
+fn parse_group_by(groups: &str) -> Result<Vec<ColumnGroup>, Vec<ErrorDetails>> {
+    let mut errors: Vec<ErrorDetails> = vec![];
+    let mut group_list: Vec<ColumnGroup> = vec![];
+
+    for group in groups.split(",") {
+        if group.is_empty() {
+            errors.push(ErrorDetails::bad_request(format!("Group '{}' contains an empty property name", groups)));
+            continue;
+        }
+
+        if !group.contains("(") {
+            group_list.push(ColumnGroup {
+                property: group.to_string(),
+                truncate: None,
+                alias: group.to_string(),
+            });
+            continue;
+        }
+
+        match split_call(group).map(|(truncate, property)| (QueryTruncate::from_str(truncate), property)) {
+            Some((Ok(truncate), property)) => {
+                group_list.push(ColumnGroup {
+                    property: property.to_string(),
+                    alias: format!("{}({})", truncate.as_str(), property),
+                    truncate: Some(truncate),
+                });
+            }
+            _ => {
+                errors.push(ErrorDetails::bad_request(format!(
+                    "Invalid group '{}', expected a property or day, week, month or year of a property",
+                    group
+                )));
             }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(group_list)
+}
+
+/// Parses `aggregate=count(*),max(created_on)`, only `count` takes `*`
+fn parse_aggregate(aggregates: &str) -> Result<Vec<ColumnAggregate>, Vec<ErrorDetails>> {
+    let mut errors: Vec<ErrorDetails> = vec![];
+    let mut aggregate_list: Vec<ColumnAggregate> = vec![];
+
+    for aggregate in aggregates.split(",") {
+        match split_call(aggregate).map(|(function, property)| (QueryAggregate::from_str(function), property)) {
+            Some((Ok(QueryAggregate::COUNT), "*")) => {
+                aggregate_list.push(ColumnAggregate {
+                    function: QueryAggregate::COUNT,
+                    property: None,
+                    alias: "count(*)".to_string(),
+                });
+            }
+            Some((Ok(_), "*")) => {
+                errors.push(ErrorDetails::bad_request(format!("Invalid aggregate '{}', only count can take *", aggregate)));
+            }
+            Some((Ok(function), property)) => {
+                aggregate_list.push(ColumnAggregate {
+                    alias: format!("{}({})", function.as_str(), property),
+                    function,
+                    property: Some(property.to_string()),
+                });
+            }
+            _ => {
+                errors.push(ErrorDetails::bad_request(format!(
+                    "Invalid aggregate '{}', expected count, sum, avg, min or max of a property",
+                    aggregate
+                )));
+            }
+        }
+    }
 
-            let mut column_filter_list = ColumnFilterList {
-                operator: column_filter_list_operator,
-                filter_list: vec![],
-            };
+    if !errors.is_empty() {
+        return Err(errors);
+    }
 
-            let mut operator = QueryOperator::AND;
-            let mut query_filter = QueryFilter::EQ;
-            let filter_variables: Vec<_> = cleaned_filter.split("=").collect();
-            let value = filter_variables.last().unwrap().to_string();
-            let mut property = filter_variables.first().unwrap().to_string();
+    Ok(aggregate_list)
+}
 
-            if property.is_empty() || value.is_empty() {
+fn parse_limit(limit: &str) -> Result<u64, ErrorDetails> {
+    match limit.parse::<u64>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(ErrorDetails::bad_request(format!("Invalid limit '{}', expected a number greater than 0", limit))),
+    }
+}
+
+/// Parses `sort_by=desc(created_on),asc_nulls_first(last_name,first_name)` into sorts kept in the given order
+fn parse_sort_by(sorts: &str) -> Result<Vec<ColumnSort>, Vec<ErrorDetails>> {
+    let mut errors: Vec<ErrorDetails> = vec![];
+
+    let sorts_seperated: Vec<_> = sorts
+        .split("),")
+        .map(|s| {
+            if s.ends_with(")") {
+                s.to_string()
+            } else {
+                format!("{})", s)
+            }
+        })
+        .collect();
+
+    let mut sort_list: Vec<ColumnSort> = vec![];
+    for sort in &sorts_seperated {
+        let Some((query_sort, fields)) = sort.split_once("(") else {
+            errors.push(ErrorDetails::bad_request(format!("Invalid sort '{}', expected asc(field) or desc(field)", sort)));
+            continue;
+        };
+
+        let (direction, nulls) = match query_sort.to_lowercase().split_once("_nulls_") {
+            Some((direction, nulls)) => (direction.to_string(), Some(nulls.to_string())),
+            None => (query_sort.to_string(), None),
+        };
+
+        let query_sort_value = match QuerySort::from_str(&direction) {
+            Ok(parsed_sort) => parsed_sort,
+            Err(_) => {
+                errors.push(ErrorDetails::bad_request(format!("Invalid sort direction '{}', expected asc or desc", query_sort)));
                 continue;
             }
+        };
 
-            let temp_property = property.clone();
-            let mut find_operator_or_filters: Vec<_> = temp_property.split("[").collect();
-            if let Some(first_filter) = find_operator_or_filters.first() {
-                property = first_filter.to_string();
+        let query_nulls_value = match nulls.map(|nulls| QueryNulls::from_str(&nulls)) {
+            None => None,
+            Some(Ok(parsed_nulls)) => Some(parsed_nulls),
+            Some(Err(_)) => {
+                errors.push(ErrorDetails::bad_request(format!("Invalid null ordering '{}', expected nulls_first or nulls_last", query_sort)));
+                continue;
             }
+        };
 
-            find_operator_or_filters.remove(0);
-            for (index, operator_or_filter) in find_operator_or_filters.iter().enumerate() {
-                let modified_operator_or_filter = operator_or_filter.to_string().replace("]", "");
-
-                if index == 0 {
-                    match QueryOperator::from_str(&*modified_operator_or_filter) {
-                        Ok(parsed_operator) => match parsed_operator {
-                            QueryOperator::AND => {
-                                // Do nothing, use default
-                                continue;
-                            }
-                            QueryOperator::OR => {
-                                operator = QueryOperator::OR;
-                                continue;
-                            }
-                        },
-                        Err(_) => {
-                            // TODO: handle errors properly
-                            println!("Query operator is neither AND nor OR");
-                        }
-                    }
-                }
+        let fields = fields.trim_end_matches(")");
+        if fields.split(",").any(|field| field.is_empty()) {
+            errors.push(ErrorDetails::bad_request(format!("Sort '{}' is missing a property name", sort)));
+            continue;
+        }
 
-                match QueryFilter::from_str(&*modified_operator_or_filter) {
-                    Ok(parsed_filter) => match parsed_filter {
-                        QueryFilter::GT => {
-                            query_filter = QueryFilter::GT;
-                        }
-                        QueryFilter::GTE => {
-                            query_filter = QueryFilter::GTE;
-                        }
-                        QueryFilter::LT => {
-                            query_filter = QueryFilter::LT;
-                        }
-                        QueryFilter::LTE => {
-                            query_filter = QueryFilter::LTE;
-                        }
-                        QueryFilter::EQ => {
-                            // Do nothing, use default
-                        }
-                        QueryFilter::NE => {
-                            query_filter = QueryFilter::NE;
-                        }
-                        QueryFilter::LIKE => {
-                            query_filter = QueryFilter::LIKE;
-                        }
-                        QueryFilter::CURSOR => {
-                            query_filter = QueryFilter::CURSOR;
-                        }
-                    },
-                    Err(_) => {
-                        // TODO: handle errors properly
-                        println!("Query filter is neither GT, GTE, LT, LTE, EQ, NE, LIKE, nor CURSOR");
-                    }
-                }
+        sort_list.extend(fields.split(",").map(|field| ColumnSort {
+            property: field.to_string(),
+            sort: query_sort_value.clone(),
+            nulls: query_nulls_value.clone(),
+        }));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(sort_list)
+}
+
+fn parse_filter(key: &str, value: &str) -> Result<Option<ColumnFilterList>, Vec<ErrorDetails>> {
+    if key.is_empty() && value.is_empty() {
+        return Ok(None);
+    }
+
+    let mut column_filter_list_operator = QueryOperator::AND;
+    let cleaned_key;
+
+    match key {
+        s if s.starts_with(&format!("[{:?}]", QueryOperator::OR).to_lowercase()) => {
+            column_filter_list_operator = QueryOperator::OR;
+
+            cleaned_key = key.trim_start_matches(&format!("[{:?}]", QueryOperator::OR).to_lowercase());
+        }
+        s if s.starts_with(&format!("[{:?}]", QueryOperator::AND).to_lowercase()) => {
+            cleaned_key = key.trim_start_matches(&format!("[{:?}]", QueryOperator::AND).to_lowercase());
+        }
+        s if s.starts_with(&format!("[{:?}]", QueryOperator::OR)) => {
+            column_filter_list_operator = QueryOperator::OR;
+
+            cleaned_key = key.trim_start_matches(&format!("[{:?}]", QueryOperator::OR));
+        }
+        s if s.starts_with(&format!("[{:?}]", QueryOperator::AND)) => {
+            cleaned_key = key.trim_start_matches(&format!("[{:?}]", QueryOperator::AND));
+        }
+        _ => {
+            cleaned_key = key;
+        }
+    }
+
+    Ok(parse_column_filter(cleaned_key, value)?.map(|column_filter| ColumnFilterList {
+        operator: column_filter_list_operator,
+        filter_list: vec![column_filter],
+    }))
+}
+
+/// Parses a single `property[operator][filter]=value` pair, returns `None` when a filter
+/// that needs a value was given without one
+pub(crate) fn parse_column_filter(key: &str, value: &str) -> Result<Option<ColumnFilter>, Vec<ErrorDetails>> {
+    let mut errors: Vec<ErrorDetails> = vec![];
+    let mut operator = QueryOperator::AND;
+    let mut query_filter = QueryFilter::EQ;
+    let mut value = value.to_string();
+
+    let mut find_operator_or_filters: Vec<_> = key.split("[").collect();
+    let property = find_operator_or_filters.remove(0).to_string();
+
+    if property.is_empty() {
+        errors.push(ErrorDetails::bad_request(format!("Filter '{}' is missing a property name", key)));
+    }
+
+    for (index, operator_or_filter) in find_operator_or_filters.iter().enumerate() {
+        let modified_operator_or_filter = operator_or_filter.to_string().replace("]", "");
+
+        if index == 0 {
+            if let Ok(parsed_operator) = QueryOperator::from_str(&modified_operator_or_filter) {
+                operator = parsed_operator;
+                continue;
             }
+        }
 
-            let column_filter = ColumnFilter {
-                operator,
-                filter: query_filter,
-                property,
-                value,
-            };
-            column_filter_list.filter_list.push(column_filter);
+        match QueryFilter::from_str(&modified_operator_or_filter) {
+            Ok(parsed_filter) => {
+                query_filter = parsed_filter;
+            }
+            Err(_) => {
+                // An operator given after the filter is out of order, keep the filter as is
+                if QueryOperator::from_str(&modified_operator_or_filter).is_ok() {
+                    continue;
+                }
 
-            result.filter_list.push(column_filter_list);
+                errors.push(ErrorDetails::bad_request(format!(
+                    "Unknown filter or operator '{}' on property '{}'",
+                    modified_operator_or_filter, property
+                )));
+            }
         }
+    }
 
-        result
+    match query_filter {
+        // Null checks don't compare against anything, so any given value is dropped
+        QueryFilter::NULL | QueryFilter::NOTNULL => {
+            value.clear();
+        }
+        _ if value.is_empty() && errors.is_empty() => {
+            return Ok(None);
+        }
+        QueryFilter::IN | QueryFilter::NIN if value.split(",").any(|item| item.is_empty()) => {
+            errors.push(ErrorDetails::bad_request(format!("Filter '{}' contains an empty list value", key)));
+        }
+        QueryFilter::BETWEEN if value.split(",").count() != 2 || value.split(",").any(|item| item.is_empty()) => {
+            errors.push(ErrorDetails::bad_request(format!("Filter '{}' expects exactly two values, e.g. start,end", key)));
+        }
+        _ => {}
     }
 
-    pub fn remove_cursor(&mut self) {
-        self.filter_list.iter_mut().for_each(|column_filter| {
-            column_filter.filter_list.retain(|filter| filter.filter != QueryFilter::CURSOR);
-        });
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
-    pub fn set_less_than(&mut self, property: &str, value: String) {
-        let mut filter_list: Vec<ColumnFilter> = vec![];
-        let column_filter = ColumnFilter {
-            operator: QueryOperator::AND,
-            filter: QueryFilter::LT,
-            property: property.to_string(),
-            value,
-        };
-        filter_list.push(column_filter);
+    Ok(Some(ColumnFilter {
+        operator,
+        filter: query_filter,
+        property,
+        value,
+    }))
+}
 
-        let column_filter_list = ColumnFilterList {
-            operator: QueryOperator::AND,
-            filter_list,
+/// Rejection returned when the query string or search body contains malformed parameters,
+/// holds one error for every problem found.
+#[derive(Debug)]
+pub struct ParameterQueryRejection(pub Vec<ErrorDetails>);
+
+impl IntoResponse for ParameterQueryRejection {
+    fn into_response(self) -> Response {
+        let data: DataListResponse<()> = DataListResponse {
+            meta: MetaListData::default(),
+            errors: self.0,
+            data: vec![],
         };
-        self.filter_list.push(column_filter_list);
+
+        data.respond()
     }
 }
 
@@ -352,7 +849,7 @@ impl<S> FromRequestParts<S> for ParameterQueryBuilder
     where
         S: Send + Sync,
 {
-    type Rejection = Infallible;
+    type Rejection = ParameterQueryRejection;
 
     async fn from_request_parts(
         parts: &mut Parts,
@@ -361,62 +858,311 @@ impl<S> FromRequestParts<S> for ParameterQueryBuilder
         let query = parts.uri.query()
                          .map(|query| query.to_owned());
 
-        Ok(Self(ParameterQueryResult::build_query_result(query)))
+        ParameterQueryResult::build_query_result(query)
+            .map(Self)
+            .map_err(ParameterQueryRejection)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::global::parameter_query_builder::{ColumnFilter, ColumnFilterList, ParameterQueryResult, QueryFilter, QueryOperator, QuerySort};
+    use http::StatusCode;
 
-// TODO: Handle errors properly, will need to return correct error responses
+    use crate::global::filter_expression::FilterExpression;
+    use crate::global::parameter_query_builder::{ColumnAggregate, ColumnFilter, ColumnFilterList, ColumnGroup, ColumnSort, ParameterQueryResult, QueryAggregate, QueryCount, QueryFilter, QueryNulls, QueryOperator, QuerySort, QueryTruncate};
 
     /// Limits
     #[test]
     fn given_limit_should_return_correct_limit() {
-        let result = ParameterQueryResult::build_query_result(Some("limit=35".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("limit=35".parse().unwrap())).unwrap();
 
-        assert_eq!(result.limit, 35);
+        assert_eq!(result.limit, Some(35));
     }
 
     #[test]
     fn given_limit_along_with_other_params_should_return_correct_limit() {
-        let result = ParameterQueryResult::build_query_result(Some("&test=testing&limit=155&testing=test".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("&test=testing&limit=155&testing=test".parse().unwrap())).unwrap();
+
+        assert_eq!(result.limit, Some(155));
+    }
+
+    #[test]
+    fn given_no_limit_should_leave_limit_to_resource() {
+        let result = ParameterQueryResult::build_query_result(Some("".parse().unwrap())).unwrap();
 
-        assert_eq!(result.limit, 155);
+        assert_eq!(result.limit, None);
+    }
+
+    #[test]
+    fn given_limit_above_three_digits_should_return_correct_limit() {
+        let result = ParameterQueryResult::build_query_result(Some("limit=1500".parse().unwrap())).unwrap();
+
+        assert_eq!(result.limit, Some(1500));
+    }
+
+    /// Pagination
+    #[test]
+    fn given_page_should_return_skip_from_limit() {
+        let result = ParameterQueryResult::build_query_result(Some("page=3&limit=20".parse().unwrap())).unwrap();
+
+        assert_eq!(result.page, Some(3));
+        assert_eq!(result.skip(20), Some(40));
+    }
+
+    #[test]
+    fn given_offset_should_return_offset_as_skip() {
+        let result = ParameterQueryResult::build_query_result(Some("offset=15".parse().unwrap())).unwrap();
+
+        assert_eq!(result.skip(20), Some(15));
+        assert!(result.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_offset_beyond_bigint_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("offset=9223372036854775808".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err()[0].message, "Invalid offset '9223372036854775808', expected a number up to 9223372036854775807");
+    }
+
+    #[test]
+    fn given_invalid_page_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("page=0".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_page_and_offset_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("page=2&offset=10".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_page_and_cursor_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("page=2&id[cursor]=10".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_cursor_token_should_keep_it_for_validation() {
+        let result = ParameterQueryResult::build_query_result(Some("cursor=eyJzIjoiaWQifQ.c2ln".parse().unwrap())).unwrap();
+
+        assert_eq!(result.cursor, Some("eyJzIjoiaWQifQ.c2ln".to_string()));
+        assert!(result.keyset.is_none());
+        assert!(result.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_offset_and_cursor_token_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("offset=20&cursor=eyJzIjoiaWQifQ.c2ln".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err()[0].message, "Page and offset pagination can't be combined with a cursor");
+    }
+
+    #[test]
+    fn given_count_should_return_count_strategy() {
+        let default = ParameterQueryResult::build_query_result(Some("".parse().unwrap())).unwrap();
+        let estimate = ParameterQueryResult::build_query_result(Some("count=estimate".parse().unwrap())).unwrap();
+        let none = ParameterQueryResult::build_query_result(Some("count=NONE".parse().unwrap())).unwrap();
+
+        assert_eq!(default.count, QueryCount::EXACT);
+        assert_eq!(estimate.count, QueryCount::ESTIMATE);
+        assert_eq!(none.count, QueryCount::NONE);
+        assert!(none.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_count_name_should_parse_back() {
+        for count in [QueryCount::EXACT, QueryCount::ESTIMATE, QueryCount::NONE] {
+            assert_eq!(count.as_str().parse::<QueryCount>(), Ok(count));
+        }
+    }
+
+    #[test]
+    fn given_invalid_count_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("count=roughly".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err()[0].message, "Invalid count 'roughly', expected exact, estimate or none");
     }
 
     /// Sorts
     #[test]
     fn given_no_sort_should_return_default() {
-        let result = ParameterQueryResult::build_query_result(Some("".parse().unwrap()));
-        let sort_fields = vec!["id".to_string()];
-
-        assert_eq!(result.sort_list.get(&QuerySort::ASC), Some(&sort_fields));
+        let result = ParameterQueryResult::build_query_result(Some("".parse().unwrap())).unwrap();
+        let expected = vec![ColumnSort {
+            property: "id".to_string(),
+            sort: QuerySort::ASC,
+            nulls: None,
+        }];
+
+        assert_eq!(result.sort_list, expected);
     }
 
     #[test]
     fn given_single_sort_should_return_correct_sort() {
-        let result = ParameterQueryResult::build_query_result(Some("sort_by=asc(first_field)".parse().unwrap()));
-        let sort_fields = vec!["first_field".to_string()];
-
-        assert_eq!(result.sort_list.get(&QuerySort::ASC), Some(&sort_fields));
+        let result = ParameterQueryResult::build_query_result(Some("sort_by=asc(first_field)".parse().unwrap())).unwrap();
+        let expected = vec![ColumnSort {
+            property: "first_field".to_string(),
+            sort: QuerySort::ASC,
+            nulls: None,
+        }];
+
+        assert_eq!(result.sort_list, expected);
     }
 
     #[test]
     fn given_double_sort_should_return_correct_sorts() {
-        let result = ParameterQueryResult::build_query_result(Some("sort_by=asc(first_field),desc(second_field)".parse().unwrap()));
-        let asc_sort_fields = vec!["first_field".to_string()];
-        let desc_sort_fields = vec!["second_field".to_string()];
+        let result = ParameterQueryResult::build_query_result(Some("sort_by=asc(first_field),desc(second_field)".parse().unwrap())).unwrap();
+        let expected = vec![
+            ColumnSort {
+                property: "first_field".to_string(),
+                sort: QuerySort::ASC,
+                nulls: None,
+            },
+            ColumnSort {
+                property: "second_field".to_string(),
+                sort: QuerySort::DESC,
+                nulls: None,
+            },
+        ];
+
+        assert_eq!(result.sort_list, expected);
+    }
+
+    #[test]
+    fn given_sorts_should_keep_requested_order() {
+        let result = ParameterQueryResult::build_query_result(Some("sort_by=desc(created_on),asc(last_name,first_name)&sort_by=desc(id)".parse().unwrap())).unwrap();
+        let properties: Vec<(&str, QuerySort)> = result.sort_list
+            .iter()
+            .map(|column_sort| (column_sort.property.as_str(), column_sort.sort.clone()))
+            .collect();
+
+        assert_eq!(properties, vec![
+            ("created_on", QuerySort::DESC),
+            ("last_name", QuerySort::ASC),
+            ("first_name", QuerySort::ASC),
+            ("id", QuerySort::DESC),
+        ]);
+    }
+
+    #[test]
+    fn given_sort_with_null_ordering_should_return_correct_sort() {
+        let result = ParameterQueryResult::build_query_result(Some("sort_by=desc_nulls_last(updated_on)".parse().unwrap())).unwrap();
+        let expected = vec![ColumnSort {
+            property: "updated_on".to_string(),
+            sort: QuerySort::DESC,
+            nulls: Some(QueryNulls::LAST),
+        }];
+
+        assert_eq!(result.sort_list, expected);
+    }
+
+    #[test]
+    fn given_invalid_null_ordering_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("sort_by=desc_nulls_middle(updated_on)".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    /// Fields
+    #[test]
+    fn given_no_fields_should_return_empty_fields() {
+        let result = ParameterQueryResult::build_query_result(Some("".parse().unwrap())).unwrap();
+
+        assert!(result.fields.is_empty());
+    }
+
+    #[test]
+    fn given_fields_should_return_requested_fields() {
+        let result = ParameterQueryResult::build_query_result(Some("fields=id,email&fields=first_name".parse().unwrap())).unwrap();
+
+        assert_eq!(result.fields, vec!["id".to_string(), "email".to_string(), "first_name".to_string()]);
+        assert!(result.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_fields_with_empty_property_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("fields=id,,email".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    /// Search
+    #[test]
+    fn given_search_should_return_trimmed_query() {
+        let result = ParameterQueryResult::build_query_result(Some("q=+ann%20smith+".parse().unwrap())).unwrap();
+
+        assert_eq!(result.search.unwrap().query, "ann smith");
+    }
+
+    #[test]
+    fn given_empty_search_should_be_ignored() {
+        let result = ParameterQueryResult::build_query_result(Some("q=".parse().unwrap())).unwrap();
+
+        assert_eq!(result.search, None);
+        assert!(result.filter_list.is_empty());
+    }
+
+    /// Includes
+    #[test]
+    fn given_includes_should_return_them_once_in_order() {
+        let result = ParameterQueryResult::build_query_result(Some("include=roles,permissions&include=roles".parse().unwrap())).unwrap();
+
+        assert_eq!(result.include, vec!["roles".to_string(), "permissions".to_string()]);
+        assert!(result.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_include_with_empty_name_should_return_error() {
+        let errors = ParameterQueryResult::build_query_result(Some("include=roles,".parse().unwrap())).unwrap_err();
+
+        assert_eq!(errors[0].message, "Include 'roles,' contains an empty relation name");
+    }
+
+    /// Aggregates
+    #[test]
+    fn given_group_by_should_return_groups_with_truncation() {
+        let result = ParameterQueryResult::build_query_result(Some("group_by=day(createdOn),roles.name".parse().unwrap())).unwrap();
 
-        assert_eq!(result.sort_list.get(&QuerySort::ASC), Some(&asc_sort_fields));
-        assert_eq!(result.sort_list.get(&QuerySort::DESC), Some(&desc_sort_fields));
+        assert_eq!(result.group_list, vec![
+            ColumnGroup { property: "createdOn".to_string(), truncate: Some(QueryTruncate::DAY), alias: "day(createdOn)".to_string() },
+            ColumnGroup { property: "roles.name".to_string(), truncate: None, alias: "roles.name".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn given_aggregates_should_return_functions_in_order() {
+        let result = ParameterQueryResult::build_query_result(Some("aggregate=COUNT(*),max(createdOn)".parse().unwrap())).unwrap();
+
+        assert_eq!(result.aggregate_list, vec![
+            ColumnAggregate { function: QueryAggregate::COUNT, property: None, alias: "count(*)".to_string() },
+            ColumnAggregate { function: QueryAggregate::MAX, property: Some("createdOn".to_string()), alias: "max(createdOn)".to_string() },
+        ]);
+        assert!(result.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_invalid_groups_and_aggregates_should_return_errors() {
+        let errors = ParameterQueryResult::build_query_result(Some(
+            "group_by=hour(createdOn),&aggregate=sum(*),median(id),count".parse().unwrap()
+        )).unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+        assert_eq!(messages, vec![
+            "Invalid group 'hour(createdOn)', expected a property or day, week, month or year of a property",
+            "Group 'hour(createdOn),' contains an empty property name",
+            "Invalid aggregate 'sum(*)', only count can take *",
+            "Invalid aggregate 'median(id)', expected count, sum, avg, min or max of a property",
+            "Invalid aggregate 'count', expected count, sum, avg, min or max of a property",
+        ]);
     }
 
     /// Filters
     #[test]
     fn given_no_filter_should_return_empty_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("".parse().unwrap())).unwrap();
         let expected: Vec<ColumnFilterList> = vec![];
 
         assert_eq!(result.filter_list, expected);
@@ -424,7 +1170,7 @@ mod tests {
 
     #[test]
     fn given_single_filter_with_no_filter_or_operator_value_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::EQ,
@@ -442,7 +1188,7 @@ mod tests {
 
     #[test]
     fn given_multi_filter_with_no_filter_or_operator_value_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name=value&second_field=value_two&third_field=value_three".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name=value&second_field=value_two&third_field=value_three".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::EQ,
@@ -487,7 +1233,7 @@ mod tests {
 
     #[test]
     fn given_greater_than_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[gt]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[gt]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::GT,
@@ -505,7 +1251,7 @@ mod tests {
 
     #[test]
     fn given_greater_than_or_equal_to_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[gte]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[gte]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::GTE,
@@ -523,7 +1269,7 @@ mod tests {
 
     #[test]
     fn given_less_than_or_equal_to_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[lte]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[lte]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::LTE,
@@ -541,7 +1287,7 @@ mod tests {
 
     #[test]
     fn given_less_than_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[lt]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[lt]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::LT,
@@ -559,7 +1305,7 @@ mod tests {
 
     #[test]
     fn given_equal_to_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[eq]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[eq]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::EQ,
@@ -577,7 +1323,7 @@ mod tests {
 
     #[test]
     fn given_not_equal_to_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[ne]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[ne]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::NE,
@@ -595,7 +1341,7 @@ mod tests {
 
     #[test]
     fn given_cursor_to_filter_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[cursor]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[cursor]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::CURSOR,
@@ -613,7 +1359,7 @@ mod tests {
 
     #[test]
     fn given_filter_and_operator_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[or][gte]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[or][gte]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::OR,
             filter: QueryFilter::GTE,
@@ -631,7 +1377,7 @@ mod tests {
 
     #[test]
     fn given_filter_and_operator_with_invalid_order_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name[gte][or]=value".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name[gte][or]=value".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::GTE,
@@ -649,7 +1395,7 @@ mod tests {
 
     #[test]
     fn given_or_operator_for_list_should_return_correct_filter() {
-        let result = ParameterQueryResult::build_query_result(Some("field_name=value&[or]another_field=value_two".parse().unwrap()));
+        let result = ParameterQueryResult::build_query_result(Some("field_name=value&[or]another_field=value_two".parse().unwrap())).unwrap();
         let column_filter = ColumnFilter {
             operator: QueryOperator::AND,
             filter: QueryFilter::EQ,
@@ -675,4 +1421,213 @@ mod tests {
 
         assert_eq!(result.filter_list, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn given_text_matching_filters_should_return_correct_filters() {
+        let result = ParameterQueryResult::build_query_result(Some(
+            "a[ilike]=v&b[starts_with]=v&c[istarts_with]=v&d[ends_with]=v&e[iends_with]=v&f[ieq]=v".parse().unwrap()
+        )).unwrap();
+        let filters: Vec<QueryFilter> = result.filter_list
+            .iter()
+            .map(|column_filter_list| column_filter_list.filter_list[0].filter.clone())
+            .collect();
+
+        assert_eq!(filters, vec![
+            QueryFilter::ILIKE,
+            QueryFilter::STARTSWITH,
+            QueryFilter::ISTARTSWITH,
+            QueryFilter::ENDSWITH,
+            QueryFilter::IENDSWITH,
+            QueryFilter::IEQ,
+        ]);
+    }
+
+    #[test]
+    fn given_in_filter_should_return_correct_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("email[in]=a@x.io,b@x.io".parse().unwrap())).unwrap();
+        let column_filter = ColumnFilter {
+            operator: QueryOperator::AND,
+            filter: QueryFilter::IN,
+            property: "email".to_string(),
+            value: "a@x.io,b@x.io".to_string(),
+        };
+
+        assert_eq!(result.filter_list[0].filter_list, vec![column_filter]);
+    }
+
+    #[test]
+    fn given_not_in_filter_should_return_correct_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("id[nin]=1,2,3".parse().unwrap())).unwrap();
+
+        assert_eq!(result.filter_list[0].filter_list[0].filter, QueryFilter::NIN);
+    }
+
+    #[test]
+    fn given_in_filter_with_empty_item_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("id[in]=1,,3".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_between_filter_should_return_single_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("created_on[between]=2024-01-01,2024-02-01".parse().unwrap())).unwrap();
+        let column_filter = ColumnFilter {
+            operator: QueryOperator::AND,
+            filter: QueryFilter::BETWEEN,
+            property: "created_on".to_string(),
+            value: "2024-01-01,2024-02-01".to_string(),
+        };
+
+        assert_eq!(result.filter_list.len(), 1);
+        assert_eq!(result.filter_list[0].filter_list, vec![column_filter]);
+    }
+
+    #[test]
+    fn given_between_filter_without_two_values_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("id[between]=1&id[between]=1,2,3".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn given_null_filter_without_value_should_return_correct_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("phone[null]".parse().unwrap())).unwrap();
+        let column_filter = ColumnFilter {
+            operator: QueryOperator::AND,
+            filter: QueryFilter::NULL,
+            property: "phone".to_string(),
+            value: "".to_string(),
+        };
+
+        assert_eq!(result.filter_list[0].filter_list, vec![column_filter]);
+    }
+
+    #[test]
+    fn given_not_null_filter_with_value_should_ignore_value() {
+        let result = ParameterQueryResult::build_query_result(Some("updated_on[notnull]=true".parse().unwrap())).unwrap();
+
+        assert_eq!(result.filter_list[0].filter_list[0].filter, QueryFilter::NOTNULL);
+        assert_eq!(result.filter_list[0].filter_list[0].value, "");
+    }
+
+    #[test]
+    fn given_filter_expression_should_return_expression_tree() {
+        let result = ParameterQueryResult::build_query_result(Some("filter=(a%3D1+or+b%3D2)+and+c%5Bnull%5D&d=4".parse().unwrap())).unwrap();
+        let expected = FilterExpression::And(vec![
+            FilterExpression::Or(vec![
+                FilterExpression::Filter(ColumnFilter {
+                    operator: QueryOperator::AND,
+                    filter: QueryFilter::EQ,
+                    property: "a".to_string(),
+                    value: "1".to_string(),
+                }),
+                FilterExpression::Filter(ColumnFilter {
+                    operator: QueryOperator::AND,
+                    filter: QueryFilter::EQ,
+                    property: "b".to_string(),
+                    value: "2".to_string(),
+                }),
+            ]),
+            FilterExpression::Filter(ColumnFilter {
+                operator: QueryOperator::AND,
+                filter: QueryFilter::NULL,
+                property: "c".to_string(),
+                value: "".to_string(),
+            }),
+        ]);
+
+        assert_eq!(result.filter_expression, Some(expected));
+        assert_eq!(result.filter_list.len(), 1);
+    }
+
+    #[test]
+    fn given_invalid_filter_expression_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("filter=(a%3D1+or".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    /// Decoding
+    #[test]
+    fn given_percent_encoded_value_should_return_decoded_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("email=user%40internal.io".parse().unwrap())).unwrap();
+
+        assert_eq!(result.filter_list[0].filter_list[0].value, "user@internal.io");
+    }
+
+    #[test]
+    fn given_plus_in_value_should_return_space() {
+        let result = ParameterQueryResult::build_query_result(Some("first_name=Mary+Ann".parse().unwrap())).unwrap();
+
+        assert_eq!(result.filter_list[0].filter_list[0].value, "Mary Ann");
+    }
+
+    #[test]
+    fn given_equals_sign_in_value_should_keep_full_value() {
+        let result = ParameterQueryResult::build_query_result(Some("field_name=a=b%3Dc".parse().unwrap())).unwrap();
+
+        assert_eq!(result.filter_list[0].filter_list[0].property, "field_name");
+        assert_eq!(result.filter_list[0].filter_list[0].value, "a=b=c");
+    }
+
+    #[test]
+    fn given_encoded_brackets_should_return_correct_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("field_name%5Bgte%5D=value".parse().unwrap())).unwrap();
+        let column_filter = ColumnFilter {
+            operator: QueryOperator::AND,
+            filter: QueryFilter::GTE,
+            property: "field_name".to_string(),
+            value: "value".to_string(),
+        };
+
+        assert_eq!(result.filter_list[0].filter_list, vec![column_filter]);
+    }
+
+    #[test]
+    fn given_repeated_keys_should_keep_every_filter() {
+        let result = ParameterQueryResult::build_query_result(Some("field_name=one&field_name=two".parse().unwrap())).unwrap();
+
+        assert_eq!(result.filter_list.len(), 2);
+        assert_eq!(result.filter_list[0].filter_list[0].value, "one");
+        assert_eq!(result.filter_list[1].filter_list[0].value, "two");
+    }
+
+    /// Errors
+    #[test]
+    fn given_limit_that_is_not_a_number_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("limit=abc".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_invalid_sort_direction_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("sort_by=up(first_field)".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_unknown_filter_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("field_name[unknown]=value".parse().unwrap()));
+        let errors = result.unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].status_code, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn given_filter_with_empty_property_should_return_error() {
+        let result = ParameterQueryResult::build_query_result(Some("[gt]=value".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn given_multiple_invalid_parameters_should_return_every_error() {
+        let result = ParameterQueryResult::build_query_result(Some("limit=abc&sort_by=up(first_field)&field_name[unknown]=value".parse().unwrap()));
+
+        assert_eq!(result.unwrap_err().len(), 3);
+    }
+}
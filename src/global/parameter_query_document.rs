@@ -0,0 +1,265 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use axum::extract::{FromRequest, Json};
+use axum::http::Request;
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value as JsonValue;
+
+use crate::global::error_handling::ErrorDetails;
+use crate::global::parameter_query_builder::{ParameterQueryRejection, ParameterQueryResult, RESERVED_PARAMETERS};
+
+/// JSON form of the query string for filter sets that don't fit in a URL, e.g.
+/// `{"limit": 20, "sort_by": ["desc(createdOn)"], "filter": "not phone[null]", "filters": {"id[in]": [1, 2]}}`
+///
+/// Every entry is turned back into the query string parameter it stands for, so both forms
+/// share the same parsing and validation. Lists repeat the parameter for top level entries and
+/// become comma separated values for filters, `null` leaves a parameter out or gives a filter
+/// an empty value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParameterQueryDocument {
+    pub limit: JsonValue,
+    pub page: JsonValue,
+    pub offset: JsonValue,
+    pub cursor: JsonValue,
+    pub count: JsonValue,
+    pub sort_by: JsonValue,
+    pub fields: JsonValue,
+    pub filter: JsonValue,
+    pub q: JsonValue,
+    pub include: JsonValue,
+    pub group_by: JsonValue,
+    pub aggregate: JsonValue,
+    /// Filters keyed like their query string parameter, kept in document order
+    #[serde(deserialize_with = "ordered_entries")]
+    pub filters: Vec<(String, JsonValue)>,
+}
+
+impl ParameterQueryDocument {
+    pub fn into_query_result(self) -> Result<ParameterQueryResult, Vec<ErrorDetails>> {
+        let mut parameters: Vec<(String, String)> = vec![];
+        let mut errors: Vec<ErrorDetails> = vec![];
+
+        let reserved = [
+            ("limit", self.limit),
+            ("page", self.page),
+            ("offset", self.offset),
+            ("cursor", self.cursor),
+            ("count", self.count),
+            ("sort_by", self.sort_by),
+            ("fields", self.fields),
+            ("filter", self.filter),
+            ("q", self.q),
+            ("include", self.include),
+            ("group_by", self.group_by),
+            ("aggregate", self.aggregate),
+        ];
+        for (key, value) in reserved {
+            let values = match value {
+                JsonValue::Array(values) => values,
+                value => vec![value],
+            };
+
+            for value in values.iter().filter(|value| !value.is_null()) {
+                match scalar_value(value) {
+                    Some(value) => parameters.push((key.to_string(), value)),
+                    None => errors.push(invalid_value(key)),
+                }
+            }
+        }
+
+        for (key, value) in self.filters {
+            if RESERVED_PARAMETERS.contains(&key.as_str()) {
+                errors.push(ErrorDetails::bad_request(format!("Filter '{}' is reserved, use the top level '{}' entry", key, key)));
+                continue;
+            }
+
+            let value = match &value {
+                JsonValue::Null => Some(String::new()),
+                JsonValue::Array(values) => values
+                    .iter()
+                    .map(scalar_value)
+                    .collect::<Option<Vec<String>>>()
+                    .map(|values| values.join(",")),
+                value => scalar_value(value),
+            };
+
+            match value {
+                Some(value) => parameters.push((key, value)),
+                None => errors.push(invalid_value(&key)),
+            }
+        }
+
+        match ParameterQueryResult::from_parameters(parameters) {
+            Ok(result) if errors.is_empty() => Ok(result),
+            Ok(_) => Err(errors),
+            Err(parameter_errors) => {
+                errors.extend(parameter_errors);
+                Err(errors)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ParameterQueryResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ParameterQueryDocument::deserialize(deserializer)?
+            .into_query_result()
+            .map_err(|errors| {
+                let messages: Vec<String> = errors.into_iter().map(|error| error.message).collect();
+                de::Error::custom(messages.join(", "))
+            })
+    }
+}
+
+/// Extracts the query from a JSON request body, see [`ParameterQueryDocument`]
+#[derive(Debug)]
+pub struct ParameterQueryBody(pub ParameterQueryResult);
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for ParameterQueryBody
+    where
+        Json<ParameterQueryDocument>: FromRequest<S, B, Rejection = axum::extract::rejection::JsonRejection>,
+        S: Send + Sync,
+        B: Send + 'static,
+{
+    type Rejection = ParameterQueryRejection;
+
+    async fn from_request(request: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(document) = Json::<ParameterQueryDocument>::from_request(request, state)
+            .await
+            .map_err(|rejection| ParameterQueryRejection(vec![ErrorDetails {
+                status_code: rejection.status(),
+                message: rejection.body_text(),
+                retry_after: None,
+            }]))?;
+
+        document
+            .into_query_result()
+            .map(Self)
+            .map_err(ParameterQueryRejection)
+    }
+}
+
+fn scalar_value(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(value) => Some(value.clone()),
+        JsonValue::Number(value) => Some(value.to_string()),
+        JsonValue::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn invalid_value(key: &str) -> ErrorDetails {
+    ErrorDetails::bad_request(format!("Invalid value for '{}', expected a string, number, boolean or a list of them", key))
+}
+
+/// Reads a JSON object into its entries in document order, repeated keys are kept
+fn ordered_entries<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, JsonValue)>, D::Error> {
+    struct EntriesVisitor;
+
+    impl<'de> Visitor<'de> for EntriesVisitor {
+        type Value = Vec<(String, JsonValue)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an object of filters")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = vec![];
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(EntriesVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use crate::global::parameter_query_builder::ParameterQueryResult;
+    use crate::global::parameter_query_document::ParameterQueryDocument;
+
+    fn from_json(document: &str) -> ParameterQueryResult {
+        serde_json::from_str::<ParameterQueryDocument>(document)
+            .unwrap()
+            .into_query_result()
+            .unwrap()
+    }
+
+    fn from_query(query: &str) -> ParameterQueryResult {
+        ParameterQueryResult::build_query_result(Some(query.to_string())).unwrap()
+    }
+
+    #[test]
+    fn given_document_should_match_equivalent_query_string() {
+        let json = from_json(r#"{
+            "limit": 20,
+            "page": "2",
+            "sort_by": ["desc_nulls_last(lastName)", "asc(id)"],
+            "fields": ["id", "email"],
+            "filter": "firstName[ilike]=ann or not phone[null]",
+            "q": "ann smith",
+            "include": ["roles", "permissions"],
+            "filters": {"id[in]": [1, 2, 3], "lastName[null]": null, "[or]email[ends_with]": "@example.com"}
+        }"#);
+        let query = from_query(
+            "limit=20&page=2&sort_by=desc_nulls_last(lastName)&sort_by=asc(id)&fields=id,email\
+            &filter=firstName%5Bilike%5D%3Dann%20or%20not%20phone%5Bnull%5D&q=ann+smith&include=roles,permissions\
+            &id[in]=1,2,3&lastName[null]&[or]email[ends_with]=%40example.com"
+        );
+
+        assert_eq!(json.filter_list, query.filter_list);
+        assert_eq!(json.filter_expression, query.filter_expression);
+        assert_eq!(json.sort_list, query.sort_list);
+        assert_eq!(json.fields, query.fields);
+        assert_eq!(json.search, query.search);
+        assert_eq!(json.include, query.include);
+        assert_eq!((json.limit, json.page, json.offset), (query.limit, query.page, query.offset));
+    }
+
+    #[test]
+    fn given_empty_document_should_use_default_sort() {
+        let json = from_json("{}");
+        let query = from_query("");
+
+        assert_eq!(json.sort_list, query.sort_list);
+        assert!(json.filter_list.is_empty());
+    }
+
+    #[test]
+    fn given_invalid_document_values_should_return_errors() {
+        let errors = serde_json::from_str::<ParameterQueryDocument>(r#"{
+            "limit": 0,
+            "filters": {"id[between]": [1], "limit": 5, "email": {"nested": true}}
+        }"#).unwrap().into_query_result().unwrap_err();
+
+        assert_eq!(errors.len(), 4);
+        assert_eq!(errors[0].status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(errors[0].message, "Filter 'limit' is reserved, use the top level 'limit' entry");
+        assert_eq!(errors[1].message, "Invalid value for 'email', expected a string, number, boolean or a list of them");
+        assert_eq!(errors[2].message, "Invalid limit '0', expected a number greater than 0");
+        assert_eq!(errors[3].message, "Filter 'id[between]' expects exactly two values, e.g. start,end");
+    }
+
+    #[test]
+    fn given_unknown_entry_should_fail_to_deserialize() {
+        assert!(serde_json::from_str::<ParameterQueryDocument>(r#"{"sort": "asc(id)"}"#).is_err());
+    }
+
+    #[test]
+    fn given_document_should_deserialize_into_query_result() {
+        let result: ParameterQueryResult = serde_json::from_str(r#"{"offset": 40, "filters": {"id[gt]": 10}}"#).unwrap();
+
+        assert_eq!(result.offset, Some(40));
+        assert_eq!(result.filter_list[0].filter_list[0].value, "10");
+        assert!(serde_json::from_str::<ParameterQueryResult>(r#"{"limit": -1}"#).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use crate::global::filter_expression::FilterExpression;
+use crate::global::parameter_query_builder::{
+    ColumnAggregate, ColumnFilter, ColumnFilterList, ColumnGroup, ColumnSort, ParameterQueryResult, QueryAggregate,
+    QueryCount, QueryFilter, QueryNulls, QueryOperator, QuerySort, QueryTruncate, TextSearch,
+};
+
+impl ParameterQueryResult {
+    /// Writes the query back as a normalized query string, parsing it with
+    /// [`ParameterQueryResult::build_query_result`] gives the same query again.
+    ///
+    /// Parameters come in a fixed order with the filters last, so equal queries give equal
+    /// strings, and the default `count=exact` is left out. Serialize a query before validating
+    /// it, validation replaces public property names with their columns.
+    pub fn to_query_string(&self) -> String {
+        let mut parameters: Vec<(String, Option<String>)> = vec![];
+        let mut push = |key: &str, value: String| parameters.push((key.to_string(), Some(value)));
+
+        if let Some(limit) = self.limit {
+            push("limit", limit.to_string());
+        }
+        if let Some(page) = self.page {
+            push("page", page.to_string());
+        }
+        if let Some(offset) = self.offset {
+            push("offset", offset.to_string());
+        }
+        if let Some(cursor) = &self.cursor {
+            push("cursor", cursor.clone());
+        }
+        if self.count != QueryCount::EXACT {
+            push("count", self.count.as_str().to_string());
+        }
+        if let Some(search) = &self.search {
+            push("q", search.query.clone());
+        }
+        if !self.sort_list.is_empty() {
+            push("sort_by", self.sort_list.iter().map(sort_value).collect::<Vec<String>>().join(","));
+        }
+        if !self.fields.is_empty() {
+            push("fields", self.fields.join(","));
+        }
+        if !self.include.is_empty() {
+            push("include", self.include.join(","));
+        }
+        if !self.group_list.is_empty() {
+            push("group_by", self.group_list.iter().map(|group| group.alias.clone()).collect::<Vec<String>>().join(","));
+        }
+        if !self.aggregate_list.is_empty() {
+            push("aggregate", self.aggregate_list.iter().map(|aggregate| aggregate.alias.clone()).collect::<Vec<String>>().join(","));
+        }
+        if let Some(filter_expression) = &self.filter_expression {
+            push("filter", filter_expression.to_string());
+        }
+
+        for filter_list in &self.filter_list {
+            for filter in &filter_list.filter_list {
+                parameters.push(filter_parameter(&filter_list.operator, filter));
+            }
+        }
+
+        parameters
+            .into_iter()
+            .map(|(key, value)| match value {
+                Some(value) => format!("{}={}", encode(&key, false), encode(&value, true)),
+                None => encode(&key, false),
+            })
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+}
+
+/// Builds `/users` style query strings without formatting them by hand, e.g.
+/// `QueryStringBuilder::new().filter("lastName", QueryFilter::EQ, "Smith").sort_by("createdOn", QuerySort::DESC).limit(20).build()`
+/// gives `limit=20&sort_by=desc(createdOn)&lastName[eq]=Smith`.
+#[derive(Debug, Clone)]
+pub struct QueryStringBuilder(ParameterQueryResult);
+
+impl Default for QueryStringBuilder {
+    fn default() -> Self {
+        QueryStringBuilder::new()
+    }
+}
+
+impl QueryStringBuilder {
+    pub fn new() -> Self {
+        QueryStringBuilder(ParameterQueryResult::empty())
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.0.limit = Some(limit);
+        self
+    }
+
+    pub fn page(mut self, page: u64) -> Self {
+        self.0.page = Some(page);
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.0.offset = Some(offset);
+        self
+    }
+
+    /// Continues from a `next` or `previous` token of an earlier response
+    pub fn cursor(mut self, cursor: &str) -> Self {
+        self.0.cursor = Some(cursor.to_string());
+        self
+    }
+
+    pub fn count(mut self, count: QueryCount) -> Self {
+        self.0.count = count;
+        self
+    }
+
+    pub fn search(mut self, query: &str) -> Self {
+        self.0.search = Some(TextSearch {
+            query: query.to_string(),
+            columns: vec![],
+        });
+        self
+    }
+
+    pub fn sort_by(mut self, property: &str, sort: QuerySort) -> Self {
+        self.0.sort_list.push(ColumnSort {
+            property: property.to_string(),
+            sort,
+            nulls: None,
+        });
+        self
+    }
+
+    pub fn sort_by_nulls(mut self, property: &str, sort: QuerySort, nulls: QueryNulls) -> Self {
+        self.0.sort_list.push(ColumnSort {
+            property: property.to_string(),
+            sort,
+            nulls: Some(nulls),
+        });
+        self
+    }
+
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.0.fields.extend(fields.iter().map(|field| field.to_string()));
+        self
+    }
+
+    pub fn include(mut self, path: &str) -> Self {
+        if !self.0.include.iter().any(|included| included == path) {
+            self.0.include.push(path.to_string());
+        }
+        self
+    }
+
+    pub fn group_by(mut self, property: &str, truncate: Option<QueryTruncate>) -> Self {
+        let alias = match &truncate {
+            Some(truncate) => format!("{}({})", truncate.as_str(), property),
+            None => property.to_string(),
+        };
+
+        self.0.group_list.push(ColumnGroup {
+            property: property.to_string(),
+            truncate,
+            alias,
+        });
+        self
+    }
+
+    /// Adds an aggregate, `None` as property counts rows with `count(*)`
+    pub fn aggregate(mut self, function: QueryAggregate, property: Option<&str>) -> Self {
+        let alias = format!("{}({})", function.as_str(), property.unwrap_or("*"));
+
+        self.0.aggregate_list.push(ColumnAggregate {
+            function,
+            property: property.map(String::from),
+            alias,
+        });
+        self
+    }
+
+    /// Adds a filter joined to the ones before it with `and`
+    pub fn filter(self, property: &str, filter: QueryFilter, value: &str) -> Self {
+        self.push_filter(QueryOperator::AND, property, filter, value)
+    }
+
+    /// Adds a filter joined to the ones before it with `or`
+    pub fn or_filter(self, property: &str, filter: QueryFilter, value: &str) -> Self {
+        self.push_filter(QueryOperator::OR, property, filter, value)
+    }
+
+    /// Adds a `filter=` expression, joined to an earlier one with `and`
+    pub fn filter_expression(mut self, expression: FilterExpression) -> Self {
+        self.0.filter_expression = match self.0.filter_expression.take() {
+            None => Some(expression),
+            Some(FilterExpression::And(mut expressions)) => {
+                expressions.push(expression);
+                Some(FilterExpression::And(expressions))
+            }
+            Some(existing) => Some(FilterExpression::And(vec![existing, expression])),
+        };
+        self
+    }
+
+    pub fn build(&self) -> String {
+        self.0.to_query_string()
+    }
+
+    fn push_filter(mut self, operator: QueryOperator, property: &str, filter: QueryFilter, value: &str) -> Self {
+        let value = match filter {
+            QueryFilter::NULL | QueryFilter::NOTNULL => String::new(),
+            _ => value.to_string(),
+        };
+
+        self.0.filter_list.push(ColumnFilterList {
+            operator,
+            filter_list: vec![ColumnFilter {
+                operator: QueryOperator::AND,
+                filter,
+                property: property.to_string(),
+                value,
+            }],
+        });
+        self
+    }
+}
+
+fn sort_value(column_sort: &ColumnSort) -> String {
+    match &column_sort.nulls {
+        Some(nulls) => format!("{}_{}({})", column_sort.sort.as_str(), nulls.as_str(), column_sort.property),
+        None => format!("{}({})", column_sort.sort.as_str(), column_sort.property),
+    }
+}
+
+/// Key and value of a filter, null filters are written without a value
+fn filter_parameter(list_operator: &QueryOperator, filter: &ColumnFilter) -> (String, Option<String>) {
+    let list_operator = match list_operator {
+        QueryOperator::AND => "",
+        QueryOperator::OR => "[or]",
+    };
+    let operator = match filter.operator {
+        QueryOperator::AND => "",
+        QueryOperator::OR => "[or]",
+    };
+    let key = format!("{}{}{}[{}]", list_operator, filter.property, operator, filter.filter.as_str());
+
+    match filter.filter {
+        QueryFilter::NULL | QueryFilter::NOTNULL => (key, None),
+        _ => (key, Some(filter.value.clone())),
+    }
+}
+
+/// Percent-encodes everything but unreserved characters and the brackets, parentheses, commas
+/// and stars the query syntax is written with, so the string stays readable in links and logs.
+/// Values keep `=` as only the first one of a pair separates key and value.
+pub fn encode(input: &str, is_value: bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+            | b'-' | b'.' | b'_' | b'~' | b'[' | b']' | b'(' | b')' | b',' | b'*' | b':' => {
+                encoded.push(byte as char);
+            }
+            b'=' if is_value => {
+                encoded.push('=');
+            }
+            _ => {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::global::filter_expression::FilterExpression;
+    use crate::global::parameter_query_builder::{ParameterQueryResult, QueryAggregate, QueryCount, QueryFilter, QueryNulls, QuerySort, QueryTruncate};
+    use crate::global::parameter_query_string::QueryStringBuilder;
+
+    fn parse(query: &str) -> ParameterQueryResult {
+        ParameterQueryResult::build_query_result(Some(query.to_string())).unwrap()
+    }
+
+    fn assert_round_trip(query: &str) {
+        let original = parse(query);
+        let serialized = original.to_query_string();
+        let parsed = parse(&serialized);
+
+        assert_eq!(parsed.filter_list, original.filter_list, "{}", serialized);
+        assert_eq!(parsed.filter_expression, original.filter_expression, "{}", serialized);
+        assert_eq!(parsed.sort_list, original.sort_list, "{}", serialized);
+        assert_eq!(parsed.fields, original.fields, "{}", serialized);
+        assert_eq!(parsed.include, original.include, "{}", serialized);
+        assert_eq!(parsed.group_list, original.group_list, "{}", serialized);
+        assert_eq!(parsed.aggregate_list, original.aggregate_list, "{}", serialized);
+        assert_eq!(parsed.search, original.search, "{}", serialized);
+        assert_eq!(parsed.cursor, original.cursor, "{}", serialized);
+        assert_eq!(parsed.count, original.count, "{}", serialized);
+        assert_eq!((parsed.limit, parsed.page, parsed.offset), (original.limit, original.page, original.offset), "{}", serialized);
+        assert_eq!(parsed.to_query_string(), serialized);
+    }
+
+    #[test]
+    fn given_parsed_queries_should_round_trip() {
+        assert_round_trip("");
+        assert_round_trip("limit=20&page=3&sort_by=desc_nulls_last(lastName),asc(id)&fields=id,email");
+        assert_round_trip("offset=40&q=ann%20o%27brien&include=roles,permissions");
+        assert_round_trip("cursor=eyJzIjoiaWQifQ.c2ln&sort_by=asc(createdOn)&count=none");
+        assert_round_trip("group_by=day(createdOn),lastName&aggregate=count(*),max(updatedOn)");
+        assert_round_trip("id[in]=1,2,3&[or]email[ends_with]=%40example.com&phone[null]&lastName[or][ieq]=smith");
+        assert_round_trip("createdOn[gte]=today-7d&createdOn[lt]=now%2B1h&firstName=a%26b%3Dc");
+        assert_round_trip("filter=(firstName[eq]='Ann Marie' or not phone[null]) and not (id[gt]=5 and id[lt]=9)");
+        assert_round_trip("filter=a[eq]=1 or (b[eq]=2 or c[eq]=%22quoted%22)&filter=d[eq]=%22x(y)%22");
+    }
+
+    #[test]
+    fn given_equivalent_queries_should_serialize_the_same() {
+        let first = parse("sort_by=asc(id)&limit=10&lastName=Smith").to_query_string();
+        let second = parse("limit=10&lastName[eq]=Smith&sort_by=asc(id)").to_query_string();
+
+        assert_eq!(first, second);
+        assert_eq!(first, "limit=10&sort_by=asc(id)&lastName[eq]=Smith");
+    }
+
+    #[test]
+    fn given_builder_should_build_normalized_query_string() {
+        let query = QueryStringBuilder::new()
+            .filter("lastName", QueryFilter::EQ, "O'Brien & Sons")
+            .or_filter("phone", QueryFilter::NULL, "")
+            .sort_by("createdOn", QuerySort::DESC)
+            .sort_by_nulls("firstName", QuerySort::ASC, QueryNulls::FIRST)
+            .fields(&["id", "email"])
+            .include("roles")
+            .search("ann")
+            .limit(20)
+            .build();
+
+        assert_eq!(
+            query,
+            "limit=20&q=ann&sort_by=desc(createdOn),asc_nulls_first(firstName)&fields=id,email&include=roles\
+            &lastName[eq]=O%27Brien%20%26%20Sons&[or]phone[null]"
+        );
+        assert_eq!(parse(&query).filter_list[0].filter_list[0].value, "O'Brien & Sons");
+    }
+
+    #[test]
+    fn given_builder_groups_and_expressions_should_parse_back() {
+        let expression = FilterExpression::parse("firstName[ilike]=ann or lastName[ilike]=ann").unwrap();
+        let query = QueryStringBuilder::new()
+            .group_by("createdOn", Some(QueryTruncate::MONTH))
+            .aggregate(QueryAggregate::COUNT, None)
+            .aggregate(QueryAggregate::MAX, Some("updatedOn"))
+            .filter_expression(expression.clone())
+            .cursor("abc.def")
+            .count(QueryCount::ESTIMATE)
+            .build();
+        let parsed = parse(&query);
+
+        assert_eq!(query, "cursor=abc.def&count=estimate&group_by=month(createdOn)&aggregate=count(*),max(updatedOn)\
+            &filter=firstName[ilike]=ann%20or%20lastName[ilike]=ann");
+        assert_eq!(parsed.filter_expression, Some(expression));
+        assert_eq!(parsed.group_list[0].alias, "month(createdOn)");
+        assert_eq!(parsed.cursor, Some("abc.def".to_string()));
+    }
+}
@@ -1,9 +1,11 @@
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use chrono::{DateTime, Utc};
-use http::StatusCode;
+use http::header::RETRY_AFTER;
+use http::HeaderMap;
 use serde::{Deserialize, Serialize};
 
-use crate::database::query_builder::QueryResult;
+use crate::database::query_builder::{AggregateResult, QueryResult};
 use crate::global::error_handling::{ErrorDetails, ErrorDetailsDto};
 
 #[derive(Serialize, Deserialize)]
@@ -13,6 +15,13 @@ pub struct DataListResponseDto<T> {
     pub data: Vec<T>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct DataAggregateResponseDto<T> {
+    pub meta: MetaAggregateDataDto,
+    pub errors: Vec<ErrorDetailsDto>,
+    pub data: Vec<T>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct DataResponseDto<T> {
     pub meta: MetaDataDto,
@@ -28,13 +37,24 @@ pub struct MetaDataDto {
 #[derive(Serialize, Deserialize)]
 pub struct MetaListDataDto {
     pub timestamp: String,
-    pub count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
     pub page: u64,
-    pub page_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_count: Option<u64>,
     pub limit: u64,
-    pub cursor: String,
-    pub next: u64,
-    pub previous: u64,
+    pub next: Option<String>,
+    pub previous: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MetaAggregateDataDto {
+    pub timestamp: String,
+    pub count: u64,
+    pub limit: u64,
+    pub has_more: bool,
+    pub group_by: Vec<String>,
+    pub aggregate: Vec<String>,
 }
 
 pub struct DataListResponse<T> {
@@ -43,6 +63,12 @@ pub struct DataListResponse<T> {
     pub data: Vec<T>,
 }
 
+pub struct DataAggregateResponse<T> {
+    pub meta: MetaAggregateData,
+    pub errors: Vec<ErrorDetails>,
+    pub data: Vec<T>,
+}
+
 pub struct DataResponse<T> {
     pub meta: MetaData,
     pub errors: Vec<ErrorDetails>,
@@ -56,13 +82,26 @@ pub struct MetaData {
 #[derive(Clone)]
 pub struct MetaListData {
     pub timestamp: DateTime<Utc>,
-    pub count: u64,
+    /// Total of the list, `None` when it wasn't counted
+    pub count: Option<u64>,
     pub page: u64,
-    pub page_count: u64,
+    pub page_count: Option<u64>,
     pub limit: u64,
-    pub cursor: String,
-    pub next: u64,
-    pub previous: u64,
+    pub next: Option<String>,
+    pub previous: Option<String>,
+}
+
+/// Meta data of grouped rows, names the group and aggregate keys found in every row
+#[derive(Clone)]
+pub struct MetaAggregateData {
+    pub timestamp: DateTime<Utc>,
+    /// Groups in this response, at most `limit`
+    pub count: u64,
+    pub limit: u64,
+    /// Whether groups past the limit were left out, the next `page` or `offset` holds them
+    pub has_more: bool,
+    pub group_by: Vec<String>,
+    pub aggregate: Vec<String>,
 }
 
 impl MetaData {
@@ -81,22 +120,48 @@ impl MetaListData {
             page: self.page,
             page_count: self.page_count,
             limit: self.limit,
-            cursor: self.cursor.clone(),
-            next: self.next,
-            previous: self.previous,
+            next: self.next.clone(),
+            previous: self.previous.clone(),
+        }
+    }
+}
+
+impl MetaAggregateData {
+    fn to_dto(&self) -> MetaAggregateDataDto {
+        MetaAggregateDataDto {
+            timestamp: self.timestamp.to_string(),
+            count: self.count,
+            limit: self.limit,
+            has_more: self.has_more,
+            group_by: self.group_by.clone(),
+            aggregate: self.aggregate.clone(),
         }
     }
+}
 
+impl Default for MetaAggregateData {
     fn default() -> Self {
-        MetaListData {
+        MetaAggregateData {
             timestamp: Utc::now(),
             count: 0,
+            limit: 0,
+            has_more: false,
+            group_by: vec![],
+            aggregate: vec![],
+        }
+    }
+}
+
+impl Default for MetaListData {
+    fn default() -> Self {
+        MetaListData {
+            timestamp: Utc::now(),
+            count: None,
             page: 0,
-            page_count: 0,
+            page_count: None,
             limit: 0,
-            cursor: "".to_string(),
-            next: 0,
-            previous: 0,
+            next: None,
+            previous: None,
         }
     }
 }
@@ -120,26 +185,106 @@ impl<T> DataListResponse<T> {
         }
     }
 
-    pub fn respond(self) -> Result<Json<DataListResponseDto<T>>, (StatusCode, Json<DataListResponseDto<T>>)> {
-        if !self.errors.is_empty() {
-            match self.errors.first() {
-                None => {
-                    Err((StatusCode::INTERNAL_SERVER_ERROR, Json::from(self.to_dto())))
-                }
-                Some(error) => {
-                    Err((error.status_code, Json::from(self.to_dto())))
-                }
-            }
-        } else {
-            Ok(Json::from(self.to_dto()))
+    /// Responds with the status of the first error, or 200 without errors
+    pub fn respond(self) -> Response
+        where
+            T: Serialize,
+    {
+        match self.errors.first() {
+            None => Json::from(self.into_dto()).into_response(),
+            Some(error) => (error.status_code, error_headers(&self.errors), Json::from(self.into_dto())).into_response(),
         }
     }
 
-    fn to_dto(self) -> DataListResponseDto<T> {
+    fn into_dto(self) -> DataListResponseDto<T> {
         DataListResponseDto {
             meta: self.meta.to_dto(),
             errors: self.errors.into_iter().map(|error| error.to_dto()).collect(),
             data: self.data,
         }
     }
-}
\ No newline at end of file
+}
+
+impl<T> DataAggregateResponse<T> {
+    pub async fn init(
+        result: Option<AggregateResult<T>>,
+        errors: Option<Vec<ErrorDetails>>,
+    ) -> DataAggregateResponse<T>
+    {
+        match result {
+            Some(result) => Self {
+                meta: result.meta,
+                errors: errors.unwrap_or_default(),
+                data: result.data,
+            },
+            None => Self {
+                meta: MetaAggregateData::default(),
+                errors: errors.unwrap_or_default(),
+                data: vec![],
+            },
+        }
+    }
+
+    /// Responds with the status of the first error, or 200 without errors
+    pub fn respond(self) -> Response
+        where
+            T: Serialize,
+    {
+        match self.errors.first() {
+            None => Json::from(self.into_dto()).into_response(),
+            Some(error) => (error.status_code, error_headers(&self.errors), Json::from(self.into_dto())).into_response(),
+        }
+    }
+
+    fn into_dto(self) -> DataAggregateResponseDto<T> {
+        DataAggregateResponseDto {
+            meta: self.meta.to_dto(),
+            errors: self.errors.into_iter().map(|error| error.to_dto()).collect(),
+            data: self.data,
+        }
+    }
+}
+
+/// `Retry-After` of the longest wait any of the errors asks for
+fn error_headers(errors: &[ErrorDetails]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(retry_after) = errors.iter().filter_map(|error| error.retry_after).max() {
+        headers.insert(RETRY_AFTER, retry_after.into());
+    }
+
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use http::header::RETRY_AFTER;
+    use http::StatusCode;
+
+    use crate::global::error_handling::ErrorDetails;
+    use crate::global::response_builder::DataListResponse;
+
+    #[tokio::test]
+    async fn given_error_with_retry_after_should_respond_with_header() {
+        let errors = vec![
+            ErrorDetails { status_code: StatusCode::SERVICE_UNAVAILABLE, message: "unavailable".to_string(), retry_after: Some(5) },
+            ErrorDetails::bad_request("invalid".to_string()),
+        ];
+        let response: DataListResponse<()> = DataListResponse::init(None, Some(errors)).await;
+
+        let response = response.respond();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "5");
+    }
+
+    #[tokio::test]
+    async fn given_error_without_retry_after_should_respond_without_header() {
+        let errors = vec![ErrorDetails::bad_request("invalid".to_string())];
+        let response: DataListResponse<()> = DataListResponse::init(None, Some(errors)).await;
+
+        let response = response.respond();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.headers().get(RETRY_AFTER).is_none());
+    }
+}
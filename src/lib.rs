@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use axum::Router;
+use sea_orm::{Database, DatabaseConnection};
+
+use crate::users::routes::user_routes;
+
+mod tests;
+mod users;
+mod global;
+mod database;
+mod authorization;
+
+pub use crate::database::cursor::init_cursor_secret;
+pub use crate::database::query_guard::init_query_limits;
+
+/// Client side of list queries, for services calling endpoints such as `/users`
+pub use crate::global::filter_expression::FilterExpression;
+pub use crate::global::parameter_query_builder::{QueryAggregate, QueryCount, QueryFilter, QueryNulls, QuerySort, QueryTruncate};
+pub use crate::global::parameter_query_string::{encode, QueryStringBuilder};
+
+#[derive(Clone)]
+pub struct AppState {
+    /// Primary database, for writes and for reads that have to see them
+    db: DatabaseConnection,
+    /// Read replica from `DATABASE_REPLICA_URL`, may lag behind the primary
+    replica: Option<DatabaseConnection>,
+}
+
+impl AppState {
+    /// Connection for reads that can be slightly stale, handlers opt in to it one by one.
+    /// Falls back to the primary when no replica is configured.
+    pub fn replica(&self) -> &DatabaseConnection {
+        self.replica.as_ref().unwrap_or(&self.db)
+    }
+}
+
+pub async fn app(db_url: String, replica_url: Option<String>) -> Router {
+    let db: DatabaseConnection = Database::connect(db_url).await.expect("Cannot find posts in page");
+    let replica = match replica_url {
+        Some(replica_url) => Some(Database::connect(replica_url).await.expect("Cannot connect to the read replica")),
+        None => None,
+    };
+    let state = Arc::new(AppState { db, replica });
+
+    Router::new()
+        .merge(user_routes())
+        .with_state(state)
+}
@@ -1,41 +1,25 @@
 use std::env;
-use std::sync::Arc;
 
-use axum::Router;
-use sea_orm::{Database, DatabaseConnection};
-
-use crate::users::routes::user_routes;
-
-mod tests;
-mod users;
-mod global;
-mod database;
-
-#[derive(Clone)]
-pub struct AppState {
-    db: DatabaseConnection,
-}
+use axum_template::{app, init_cursor_secret, init_query_limits};
+use tracing::Level;
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
+    tracing_subscriber::fmt().with_max_level(Level::WARN).init();
 
     let host = env::var("HOST").expect("HOST is not set in .env file");
     let port = env::var("PORT").expect("PORT is not set in .env file");
     let address = host + ":" + &*port;
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL is not set in .env file");
+    let cursor_secret = env::var("CURSOR_SECRET").expect("CURSOR_SECRET is not set in .env file");
+    init_cursor_secret(cursor_secret).unwrap_or_else(|error| panic!("{}", error));
+    init_query_limits(env::var("STATEMENT_TIMEOUT_MS").ok(), env::var("QUERY_MAX_COST").ok())
+        .unwrap_or_else(|error| panic!("{}", error));
+    let replica_url = env::var("DATABASE_REPLICA_URL").ok().filter(|url| !url.is_empty());
 
     axum::Server::bind(&address.parse().unwrap())
-        .serve(app(db_url).await.into_make_service())
+        .serve(app(db_url, replica_url).await.into_make_service())
         .await
         .unwrap();
 }
-
-pub async fn app(db_url: String) -> Router {
-    let db: DatabaseConnection = Database::connect(db_url).await.expect("Cannot find posts in page");
-    let state = Arc::new(AppState { db });
-
-    Router::new()
-        .merge(user_routes())
-        .with_state(state)
-}
\ No newline at end of file
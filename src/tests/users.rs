@@ -11,7 +11,7 @@ const TAG: &str = "11-alpine";
 /// This module is based on the official [`Postgres docker image`].
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// use testcontainers::clients;
 /// use testcontainers_modules::postgres;
 ///
@@ -116,7 +116,7 @@ mod users {
         };
         db.execute(statement).await.expect("TODO: panic message");
 
-        let app = app(connection_string.clone())
+        let app = app(connection_string.clone(), None)
             .await
             .into_make_service();
         let server = TestServer::new(app).unwrap();
@@ -125,5 +125,14 @@ mod users {
             .await;
 
         assert_eq!(response.status_code(), StatusCode::OK);
+
+        let response = server
+            .get("/users")
+            .add_query_param("fields", "firstName")
+            .await;
+        let body = response.json::<serde_json::Value>();
+
+        assert_eq!(response.status_code(), StatusCode::OK);
+        assert_eq!(body["data"][0], serde_json::json!({"firstName": "User"}));
     }
 }
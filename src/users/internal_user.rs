@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "internal_user")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub created_on: Option<DateTimeWithTimeZone>,
+    pub updated_on: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::user::Entity",
+        from = "Column::UserId",
+        to = "crate::users::user::Column::Id"
+    )]
+    User,
+    #[sea_orm(has_many = "crate::authorization::role_internal_user::Entity")]
+    RoleInternalUser,
+}
+
+impl Related<crate::users::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<crate::authorization::role_internal_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoleInternalUser.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,3 +1,4 @@
 pub mod user;
+pub mod internal_user;
 pub mod routes;
 mod user_management;
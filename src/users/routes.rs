@@ -1,31 +1,67 @@
 use std::sync::Arc;
 
-use axum::{Json, Router, routing::get};
+use axum::{Router, routing::{get, post}};
 use axum::extract::State;
-use axum::http::StatusCode;
-use sea_orm::EntityTrait;
+use axum::response::Response;
+use sea_orm::JsonValue;
 
 use crate::AppState;
-use crate::global::parameter_query_builder::ParameterQueryBuilder;
-use crate::global::response_builder::{DataListResponse, DataListResponseDto};
-use crate::users::user::Model;
-use crate::users::user_management::get_all;
+use crate::global::parameter_query_builder::{ParameterQueryBuilder, ParameterQueryResult};
+use crate::global::parameter_query_document::ParameterQueryBody;
+use crate::global::response_builder::{DataAggregateResponse, DataListResponse};
+use crate::users::user_management::{get_aggregate, get_all};
 
 // TODO: Finish all user routes
 pub async fn find_all(
     state: State<Arc<AppState>>,
     ParameterQueryBuilder(parameter_query_result): ParameterQueryBuilder,
-) -> Result<Json<DataListResponseDto<Model>>, (StatusCode, Json<DataListResponseDto<Model>>)> {
-    let users = get_all(&state.db, parameter_query_result).await;
+) -> Response {
+    list_users(&state, parameter_query_result).await
+}
+
+/// Same as [`find_all`] with the query given as a JSON body, for filter sets too large for a URL
+pub async fn search(
+    state: State<Arc<AppState>>,
+    ParameterQueryBody(parameter_query_result): ParameterQueryBody,
+) -> Response {
+    list_users(&state, parameter_query_result).await
+}
+
+/// Grouped rows for `group_by=` and `aggregate=`, filters apply to the rows before grouping.
+/// Reporting reads, served by the replica.
+pub async fn aggregate(
+    state: State<Arc<AppState>>,
+    ParameterQueryBuilder(parameter_query_result): ParameterQueryBuilder,
+) -> Response {
+    match get_aggregate(state.replica(), parameter_query_result).await {
+        Ok(groups) => {
+            let data: DataAggregateResponse<JsonValue> = DataAggregateResponse::init(Some(groups), None).await;
+
+            data.respond()
+        }
+        Err(errors) => {
+            let data: DataAggregateResponse<JsonValue> = DataAggregateResponse::init(None, Some(errors)).await;
+
+            data.respond()
+        }
+    }
+}
+
+/// Read only, served by the replica
+async fn list_users(
+    state: &AppState,
+    parameter_query_result: ParameterQueryResult,
+) -> Response {
+    let users = get_all(state.replica(), parameter_query_result).await;
 
     match users {
         Ok(users) => {
-            let data: DataListResponse<Model> = DataListResponse::init(Some(users), None).await;
+            let data: DataListResponse<JsonValue> = DataListResponse::init(Some(users), None).await;
 
             data.respond()
         }
         Err(errors) => {
-            let data: DataListResponse<Model> = DataListResponse::init(None, Some(errors)).await;
+            let data: DataListResponse<JsonValue> = DataListResponse::init(None, Some(errors)).await;
 
             data.respond()
         }
@@ -35,12 +71,14 @@ pub async fn find_all(
 // pub async fn find(
 //     state: State<Arc<AppState>>,
 //     ParameterQueryBuilder(parameter_query_result): ParameterQueryBuilder,
-// ) -> Result<Json<DataListResponseDto<Model>>, (StatusCode, Json<DataListResponseDto<Model>>)> {
+// ) -> Response {
 //
 // }
 
 pub fn user_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/users", get(find_all))
+        .route("/users/search", post(search))
+        .route("/users/aggregate", get(aggregate))
     // .route("/user", get(find))
 }
\ No newline at end of file
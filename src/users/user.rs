@@ -1,6 +1,11 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::authorization::{permission, role, role_internal_user, role_permission};
+use crate::database::query_resource::{EQUALITY_FILTERS, NULL_FILTERS, QueryField, QueryRelation, QueryResource, RANGE_FILTERS, TEXT_FILTERS};
+use crate::global::parameter_query_builder::QueryFilter;
+use crate::users::internal_user;
+
 #[derive(Serialize, Deserialize)]
 pub struct Dto {
     pub id: Option<u64>,
@@ -38,6 +43,89 @@ pub struct Model {
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_one = "crate::users::internal_user::Entity")]
+    InternalUser,
+}
+
+impl Related<crate::users::internal_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::InternalUser.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
+
+impl QueryResource for Entity {
+    fn query_fields() -> Vec<QueryField> {
+        vec![
+            QueryField::new(Column::Id.as_str())
+                .filters(&EQUALITY_FILTERS)
+                .filters(&RANGE_FILTERS)
+                .filters(&[QueryFilter::CURSOR])
+                .sortable(),
+            QueryField::new(Column::FirstName.as_str())
+                .filters(&EQUALITY_FILTERS)
+                .filters(&TEXT_FILTERS)
+                .filters(&NULL_FILTERS)
+                .sortable(),
+            QueryField::new(Column::LastName.as_str())
+                .filters(&EQUALITY_FILTERS)
+                .filters(&TEXT_FILTERS)
+                .filters(&NULL_FILTERS)
+                .sortable(),
+            QueryField::new(Column::Email.as_str())
+                .filters(&EQUALITY_FILTERS)
+                .filters(&TEXT_FILTERS)
+                .sortable(),
+            QueryField::new(Column::Phone.as_str())
+                .filters(&EQUALITY_FILTERS)
+                .filters(&TEXT_FILTERS)
+                .filters(&NULL_FILTERS),
+            QueryField::new(Column::CreatedOn.as_str())
+                .filters(&RANGE_FILTERS)
+                .filters(&NULL_FILTERS)
+                .sortable(),
+            QueryField::new(Column::UpdatedOn.as_str())
+                .filters(&RANGE_FILTERS)
+                .filters(&NULL_FILTERS)
+                .sortable(),
+        ]
+    }
+
+    fn search_columns() -> Vec<&'static str> {
+        vec![Column::FirstName.as_str(), Column::LastName.as_str(), Column::Email.as_str()]
+    }
+
+    fn relations() -> Vec<QueryRelation> {
+        vec![
+            QueryRelation::new::<internal_user::Entity>("internalUser", vec![Relation::InternalUser.def()])
+                .field(QueryField::new(internal_user::Column::Id.as_str())
+                    .filters(&EQUALITY_FILTERS)
+                    .filters(&NULL_FILTERS))
+                .field(QueryField::new(internal_user::Column::CreatedOn.as_str())
+                    .filters(&RANGE_FILTERS)
+                    .sortable())
+                .includable(),
+            QueryRelation::new::<role::Entity>("roles", vec![
+                Relation::InternalUser.def(),
+                internal_user::Relation::RoleInternalUser.def(),
+                role_internal_user::Relation::Role.def(),
+            ])
+                .field(QueryField::new(role::Column::Name.as_str())
+                    .filters(&EQUALITY_FILTERS)
+                    .filters(&TEXT_FILTERS))
+                .includable(),
+            QueryRelation::new::<permission::Entity>("permissions", vec![
+                Relation::InternalUser.def(),
+                internal_user::Relation::RoleInternalUser.def(),
+                role_internal_user::Relation::Role.def(),
+                role::Relation::RolePermission.def(),
+                role_permission::Relation::Permission.def(),
+            ])
+                .field(QueryField::new(permission::Column::Name.as_str())
+                    .filters(&EQUALITY_FILTERS))
+                .includable(),
+        ]
+    }
+}
@@ -1,84 +1,93 @@
-use std::collections::HashMap;
-
 use chrono::Utc;
-use sea_orm::{DatabaseConnection, EntityTrait, PaginatorTrait};
+use sea_orm::{DatabaseConnection, JsonValue};
 
-use crate::database::query_builder::{QueryBuilder, QueryResult};
+use crate::database::query_builder::{AggregateResult, QueryBuilder, QueryResult};
+use crate::database::query_resource::QueryResource;
 use crate::global::error_handling::ErrorDetails;
-use crate::global::parameter_query_builder::{ParameterQueryResult, QuerySort};
-use crate::global::response_builder::MetaListData;
-use crate::users::user::{Entity, Model};
+use crate::global::parameter_query_builder::{ParameterQueryResult, QueryCount};
+use crate::global::response_builder::MetaAggregateData;
+use crate::users::user::Entity;
 
 pub async fn get_all(
     db: &DatabaseConnection,
-    mut query_result: ParameterQueryResult,
-) -> Result<QueryResult<Model>, Vec<ErrorDetails>> {
-    let result: Result<Vec<Model>, Vec<ErrorDetails>> = QueryBuilder::get_list::<Entity>(db, query_result.clone()).await;
-
-    let mut users: Vec<Model> = vec![];
-    match result {
-        Ok(result) => {
-            users = result;
-        }
-        Err(errors) => {
-            return Err(errors);
-        }
+    query_result: ParameterQueryResult,
+) -> Result<QueryResult<JsonValue>, Vec<ErrorDetails>> {
+    if !query_result.group_list.is_empty() || !query_result.aggregate_list.is_empty() {
+        return Err(vec![ErrorDetails::bad_request("group_by and aggregate are only supported by /users/aggregate".to_string())]);
     }
 
-    // Get current page and total page count
-    let original_query = query_result.clone();
-    let remaining_count = QueryBuilder::generate(Entity::find(), original_query.clone())
-        .count(db)
-        .await
-        .expect("Cannot count users");
-
-    query_result.remove_cursor();
-    let total_count = QueryBuilder::generate(Entity::find(), query_result.clone())
-        .count(db)
-        .await
-        .expect("Cannot count users");
-
-    // Get next and previous cursors
-    let mut next_query = original_query.clone();
-    next_query.limit += 1;
-    let next_result = QueryBuilder::generate(Entity::find(), next_query)
-        .all(db).await
-        .expect("Cannot find users");
+    let query_result = QueryBuilder::validate::<Entity>(query_result)?;
 
-    let next = if !next_result.is_empty() && users.len() as u64 == query_result.limit {
-        next_result.last().unwrap().id
-    } else {
-        0
-    };
+    QueryBuilder::paginate::<Entity>(db, query_result).await
+}
 
-    let mut previous_query = original_query.clone();
-    let mut desc_sort = HashMap::new();
-    desc_sort.insert(QuerySort::DESC, vec!["id".to_string()]);
-    previous_query.sort_list = desc_sort;
+pub async fn get_aggregate(
+    db: &DatabaseConnection,
+    query_result: ParameterQueryResult,
+) -> Result<AggregateResult<JsonValue>, Vec<ErrorDetails>> {
+    let list_parameters = [
+        ("include", !query_result.include.is_empty()),
+        ("fields", !query_result.fields.is_empty()),
+        ("cursor", query_result.cursor.is_some()),
+        ("count", query_result.count != QueryCount::EXACT),
+    ];
+    let errors: Vec<ErrorDetails> = list_parameters
+        .into_iter()
+        .filter(|(_, is_set)| *is_set)
+        .map(|(parameter, _)| ErrorDetails::bad_request(format!("{} is only supported by /users", parameter)))
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
 
-    previous_query.remove_cursor();
-    previous_query.set_less_than("id", users.first().unwrap().id.to_string());
-    let previous_result = QueryBuilder::generate(Entity::find(), previous_query)
-        .all(db).await
-        .expect("Cannot find users");
+    let query_result = QueryBuilder::validate::<Entity>(query_result)?;
+    let limit = query_result.limit.unwrap_or(Entity::default_limit());
+    let group_by = query_result.group_list.iter().map(|group| group.alias.clone()).collect();
+    let mut aggregate: Vec<String> = query_result.aggregate_list.iter().map(|aggregate| aggregate.alias.clone()).collect();
+    if aggregate.is_empty() {
+        aggregate.push("count(*)".to_string());
+    }
 
-    let previous = if !previous_result.is_empty() {
-        previous_result.last().unwrap().id
-    } else {
-        0
-    };
+    let (groups, has_more) = QueryBuilder::get_aggregate::<Entity>(db, query_result).await?;
 
-    Ok(QueryResult {
-        meta: MetaListData {
+    Ok(AggregateResult {
+        meta: MetaAggregateData {
             timestamp: Utc::now(),
-            count: total_count,
-            page: QueryBuilder::current_page(total_count, remaining_count, query_result.limit),
-            page_count: QueryBuilder::page_count(total_count, query_result.limit),
-            limit: query_result.limit,
-            cursor: "id".to_string(),
-            next: next as u64,
-            previous: previous as u64,
+            count: groups.len() as u64,
+            limit,
+            has_more,
+            group_by,
+            aggregate,
         },
-        data: users,
+        data: groups,
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use sea_orm::DatabaseConnection;
+
+    use crate::global::parameter_query_builder::ParameterQueryResult;
+    use crate::users::user_management::get_aggregate;
+
+    #[tokio::test]
+    async fn given_list_parameters_on_aggregate_should_return_errors() {
+        let query_result = ParameterQueryResult::build_query_result(Some(
+            "group_by=lastName&include=roles&fields=id&cursor=abc.def&count=none".to_string(),
+        )).unwrap();
+
+        let Err(errors) = get_aggregate(&DatabaseConnection::Disconnected, query_result).await else {
+            panic!("list parameters should be rejected");
+        };
+        let messages: Vec<String> = errors.iter().map(|error| error.message.clone()).collect();
+
+        assert!(errors.iter().all(|error| error.status_code == StatusCode::BAD_REQUEST));
+        assert_eq!(messages, [
+            "include is only supported by /users",
+            "fields is only supported by /users",
+            "cursor is only supported by /users",
+            "count is only supported by /users",
+        ]);
+    }
+}
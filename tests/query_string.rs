@@ -0,0 +1,32 @@
+use axum_template::{encode, FilterExpression, QueryAggregate, QueryCount, QueryFilter, QuerySort, QueryStringBuilder, QueryTruncate};
+
+#[test]
+fn given_public_builder_should_build_query_string() {
+    let query = QueryStringBuilder::new()
+        .filter("createdOn", QueryFilter::GTE, "2024-01-01T00:00:00+02:00")
+        .sort_by("lastName", QuerySort::ASC)
+        .count(QueryCount::NONE)
+        .limit(50)
+        .build();
+
+    assert_eq!(query, "limit=50&count=none&sort_by=asc(lastName)&createdOn[gte]=2024-01-01T00:00:00%2B02:00");
+}
+
+#[test]
+fn given_public_builder_should_build_aggregate_query_string() {
+    let expression = FilterExpression::parse("firstName[eq]=Ann or phone[null]").unwrap();
+    let query = QueryStringBuilder::new()
+        .group_by("createdOn", Some(QueryTruncate::DAY))
+        .aggregate(QueryAggregate::COUNT, None)
+        .filter_expression(expression)
+        .build();
+
+    assert_eq!(query, "group_by=day(createdOn)&aggregate=count(*)&filter=firstName[eq]=Ann%20or%20phone[null]");
+}
+
+#[test]
+fn given_reserved_characters_should_encode() {
+    assert_eq!(encode("a+b c&d", false), "a%2Bb%20c%26d");
+    assert_eq!(encode("x=1", true), "x=1");
+    assert_eq!(encode("x=1", false), "x%3D1");
+}